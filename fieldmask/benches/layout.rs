@@ -0,0 +1,122 @@
+//! Compares the default mask layout, one `FieldMask` per field, with `#[fieldmask(packed)]`.
+//!
+//! Run with `cargo bench -p fieldmask`.
+
+use std::{convert::TryFrom, hint::black_box, mem::size_of, time::Instant};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+macro_rules! leaves {
+    ($(#[$attr:meta])* $name:ident) => {
+        #[derive(Clone, Default, Maskable)]
+        $(#[$attr])*
+        struct $name {
+            f_00: u32,
+            f_01: u32,
+            f_02: u32,
+            f_03: u32,
+            f_04: u32,
+            f_05: u32,
+            f_06: u32,
+            f_07: u32,
+            f_08: u32,
+            f_09: u32,
+            f_10: u32,
+            f_11: u32,
+            f_12: u32,
+            f_13: u32,
+            f_14: u32,
+            f_15: u32,
+            f_16: u32,
+            f_17: u32,
+            f_18: u32,
+            f_19: u32,
+            f_20: u32,
+            f_21: u32,
+            f_22: u32,
+            f_23: u32,
+            f_24: u32,
+            f_25: u32,
+            f_26: u32,
+            f_27: u32,
+            f_28: u32,
+            f_29: u32,
+            f_30: u32,
+            f_31: u32,
+            f_32: u32,
+            f_33: u32,
+            f_34: u32,
+            f_35: u32,
+            f_36: u32,
+            f_37: u32,
+            f_38: u32,
+            f_39: u32,
+        }
+    };
+}
+
+leaves!(Tuple);
+leaves!(
+    #[fieldmask(packed)]
+    Packed
+);
+
+const PATHS: &[&str] = &["f_00", "f_07", "f_13", "f_21", "f_22", "f_30", "f_39"];
+const OTHER_PATHS: &[&str] = &["f_01", "f_07", "f_14", "f_21", "f_35"];
+const ITERATIONS: u32 = 1_000_000;
+
+/// The average time of an iteration of `f`, in nanoseconds.
+fn time<R>(mut f: impl FnMut() -> R) -> f64 {
+    for _ in 0..ITERATIONS / 10 {
+        black_box(f());
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed().as_secs_f64() * 1e9 / f64::from(ITERATIONS)
+}
+
+/// The time of each operation on masks of `$T`.
+macro_rules! bench {
+    ($T:ty) => {{
+        let parse = |paths: &[&str]| {
+            FieldMask::<$T>::try_from(FieldMaskInput(paths.iter().copied()))
+                .expect("unable to parse mask")
+        };
+        let a = parse(PATHS);
+        let b = parse(OTHER_PATHS);
+        let value = <$T>::default();
+        vec![
+            ("bitor", time(|| black_box(a) | black_box(b))),
+            ("bitand", time(|| black_box(a) & black_box(b))),
+            ("not", time(|| !black_box(a))),
+            ("eq", time(|| black_box(a) == black_box(b))),
+            ("is_full", time(|| black_box(a).is_full())),
+            ("parse", time(|| parse(black_box(PATHS)))),
+            ("to_paths", time(|| black_box(a).to_paths())),
+            (
+                "apply",
+                time(|| {
+                    let mut target = value.clone();
+                    black_box(a).apply(&mut target, value.clone());
+                    target
+                }),
+            ),
+        ]
+    }};
+}
+
+fn main() {
+    println!(
+        "mask size: tuple {} bytes, packed {} bytes",
+        size_of::<<Tuple as Maskable>::Mask>(),
+        size_of::<<Packed as Maskable>::Mask>(),
+    );
+    for ((name, tuple), (_, packed)) in bench!(Tuple).into_iter().zip(bench!(Packed)) {
+        println!(
+            "{:<10} tuple {:>8.2} ns  packed {:>8.2} ns",
+            name, tuple, packed,
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+};
+use std::{borrow::Cow, rc::Rc, sync::Arc};
+
+use crate::{
+    field_mask::{FieldMask, Segment, ToPathsError},
+    maskable::{
+        DeserializeMaskError, DiffMaskable, Maskable, PathInfo, SelfMaskable, SelfMaskableChanges,
+        SelfMaskableRef,
+    },
+    path::{Lift, MaskablePaths},
+};
+
+/// Mask of a `Box`, `Rc` or `Arc`.
+///
+/// The mask of the pointee is only allocated when it is neither empty nor full, so that the mask
+/// of a recursive type like `struct Node { child: Option<Box<Node>> }` is finite.
+pub struct BoxMask<T: Maskable>(State<T::Mask>);
+
+enum State<M> {
+    Empty,
+    Full,
+    Partial(Box<M>),
+}
+
+impl<T: Maskable> BoxMask<T> {
+    /// Wrap the mask of the pointee.
+    pub fn new(mask: T::Mask) -> Self {
+        if mask == T::Mask::default() {
+            BoxMask(State::Empty)
+        } else if mask == !T::Mask::default() {
+            BoxMask(State::Full)
+        } else {
+            BoxMask(State::Partial(Box::new(mask)))
+        }
+    }
+
+    /// The mask of the pointee.
+    pub fn get(&self) -> Cow<'_, T::Mask> {
+        match &self.0 {
+            State::Empty => Cow::Owned(T::Mask::default()),
+            State::Full => Cow::Owned(!T::Mask::default()),
+            State::Partial(mask) => Cow::Borrowed(mask),
+        }
+    }
+
+    /// Unwrap the mask of the pointee.
+    pub fn into_inner(self) -> T::Mask {
+        match self.0 {
+            State::Empty => T::Mask::default(),
+            State::Full => !T::Mask::default(),
+            State::Partial(mask) => *mask,
+        }
+    }
+}
+
+impl<T: Maskable> Default for BoxMask<T> {
+    fn default() -> Self {
+        BoxMask(State::Empty)
+    }
+}
+
+impl<T: Maskable> Clone for BoxMask<T> {
+    fn clone(&self) -> Self {
+        BoxMask(match &self.0 {
+            State::Empty => State::Empty,
+            State::Full => State::Full,
+            State::Partial(mask) => State::Partial(mask.clone()),
+        })
+    }
+}
+
+// Masks are normalized by `new`, so equal masks have the same state.
+impl<T: Maskable> PartialEq for BoxMask<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (State::Empty, State::Empty) | (State::Full, State::Full) => true,
+            (State::Partial(lhs), State::Partial(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+impl<T: Maskable> fmt::Debug for BoxMask<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            State::Empty => f.write_str("BoxMask(Empty)"),
+            State::Full => f.write_str("BoxMask(Full)"),
+            State::Partial(mask) => f.debug_tuple("BoxMask").field(mask).finish(),
+        }
+    }
+}
+
+impl<T: Maskable> BitAnd for BoxMask<T> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        match (self.0, rhs.0) {
+            (State::Empty, _) | (_, State::Empty) => BoxMask(State::Empty),
+            (State::Full, other) | (other, State::Full) => BoxMask(other),
+            (State::Partial(lhs), State::Partial(rhs)) => BoxMask::new(*lhs & *rhs),
+        }
+    }
+}
+
+impl<T: Maskable> BitAndAssign for BoxMask<T> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) & rhs;
+    }
+}
+
+impl<T: Maskable> BitOr for BoxMask<T> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        match (self.0, rhs.0) {
+            (State::Full, _) | (_, State::Full) => BoxMask(State::Full),
+            (State::Empty, other) | (other, State::Empty) => BoxMask(other),
+            (State::Partial(lhs), State::Partial(rhs)) => BoxMask::new(*lhs | *rhs),
+        }
+    }
+}
+
+impl<T: Maskable> BitOrAssign for BoxMask<T> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) | rhs;
+    }
+}
+
+impl<T: Maskable> BitXor for BoxMask<T> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        match (self.0, rhs.0) {
+            (State::Empty, other) | (other, State::Empty) => BoxMask(other),
+            (State::Full, other) | (other, State::Full) => !BoxMask(other),
+            (State::Partial(lhs), State::Partial(rhs)) => BoxMask::new(*lhs ^ *rhs),
+        }
+    }
+}
+
+impl<T: Maskable> BitXorAssign for BoxMask<T> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) ^ rhs;
+    }
+}
+
+impl<T: Maskable> Not for BoxMask<T> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self.0 {
+            State::Empty => BoxMask(State::Full),
+            State::Full => BoxMask(State::Empty),
+            State::Partial(mask) => BoxMask::new(!*mask),
+        }
+    }
+}
+
+macro_rules! pointer_maskable {
+    ($P:ident, $($clone:ident)?) => {
+        impl<T: Maskable> Maskable for $P<T> {
+            type Mask = BoxMask<T>;
+
+            const TYPE_STR: &'static str = T::TYPE_STR;
+
+            fn try_bitor_assign_mask(
+                mask: &mut Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<(), DeserializeMaskError> {
+                let mut inner = mask.get().into_owned();
+                T::try_bitor_assign_mask(&mut inner, field_mask_segs)?;
+                *mask = BoxMask::new(inner);
+                Ok(())
+            }
+
+            // The mask of the pointee is only built for a partial mask, since an empty one would
+            // be built again for every level of a recursive type.
+            fn append_mask_paths(
+                mask: &Self::Mask,
+                prefix: &str,
+                paths: &mut Vec<String>,
+            ) -> Result<(), ToPathsError> {
+                match &mask.0 {
+                    State::Empty => {}
+                    State::Full => paths.push(prefix.into()),
+                    State::Partial(mask) => return T::append_mask_paths(mask, prefix, paths),
+                }
+                Ok(())
+            }
+
+            fn mask_contains(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                T::mask_contains(&mask.get(), field_mask_segs)
+            }
+
+            fn mask_intersects(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                T::mask_intersects(&mask.get(), field_mask_segs)
+            }
+
+            // A recursive type has infinitely many paths, so the ones under a pointer are not
+            // listed.
+            fn append_all_paths(_prefix: &str, _paths: &mut Vec<PathInfo>) {}
+
+            fn append_json_segs<'a>(
+                field_mask_segs: &[Segment<&'a str>],
+                to_json: bool,
+                renamed: &mut Vec<Segment<&'a str>>,
+            ) -> bool {
+                T::append_json_segs(field_mask_segs, to_json, renamed)
+            }
+
+            fn append_field_names(names: &mut Vec<&'static str>) {
+                T::append_field_names(names)
+            }
+        }
+
+        impl<T: SelfMaskable $(+ $clone)?> SelfMaskable for $P<T> {
+            fn apply_mask(&mut self, src: Self, mask: Self::Mask) {
+                match mask.0 {
+                    State::Empty => {}
+                    State::Full => *self = src,
+                    State::Partial(mask) => SelfMaskable::apply_mask(
+                        pointer_maskable!(@get_mut $P, self),
+                        pointer_maskable!(@into_inner $P, src),
+                        *mask,
+                    ),
+                }
+            }
+
+            fn project_mask(&mut self, mask: &Self::Mask) {
+                if let State::Full = mask.0 {
+                    return;
+                }
+                SelfMaskable::project_mask(pointer_maskable!(@get_mut $P, self), &mask.get());
+            }
+        }
+
+        impl<T: SelfMaskableRef $(+ $clone)?> SelfMaskableRef for $P<T> {
+            fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) {
+                if let State::Empty = mask.0 {
+                    return;
+                }
+                SelfMaskableRef::apply_mask_ref(
+                    pointer_maskable!(@get_mut $P, self),
+                    &**src,
+                    &mask.get(),
+                );
+            }
+        }
+
+        impl<T: SelfMaskableChanges $(+ $clone)?> SelfMaskableChanges for $P<T> {
+            fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> Self::Mask {
+                if let State::Empty = mask.0 {
+                    return BoxMask::default();
+                }
+                BoxMask::new(SelfMaskableChanges::apply_mask_with_changes(
+                    pointer_maskable!(@get_mut $P, self),
+                    pointer_maskable!(@into_inner $P, src),
+                    mask.into_inner(),
+                ))
+            }
+        }
+
+        impl<T: DiffMaskable> DiffMaskable for $P<T> {
+            fn diff_mask(&self, other: &Self) -> Self::Mask {
+                BoxMask::new(T::diff_mask(&**self, &**other))
+            }
+        }
+
+        impl<T, R, L> Lift<T, R> for PointerLift<L, $P<T>>
+        where
+            L: Lift<$P<T>, R>,
+            T: Maskable,
+            R: Maskable,
+        {
+            fn lift(self, mask: FieldMask<T>) -> FieldMask<R> {
+                self.parent
+                    .lift(FieldMask::from_mask(BoxMask::new(mask.into_mask())))
+            }
+        }
+
+        impl<T, R, L> MaskablePaths<R, L> for $P<T>
+        where
+            T: MaskablePaths<R, PointerLift<L, $P<T>>>,
+            R: Maskable,
+            L: Lift<$P<T>, R>,
+        {
+            type Paths = T::Paths;
+
+            fn paths(lift: L) -> Self::Paths {
+                T::paths(PointerLift {
+                    parent: lift,
+                    _marker: PhantomData,
+                })
+            }
+        }
+    };
+    (@get_mut Box, $value:expr) => {
+        &mut **$value
+    };
+    (@get_mut $P:ident, $value:expr) => {
+        $P::make_mut($value)
+    };
+    (@into_inner Box, $value:expr) => {
+        *$value
+    };
+    (@into_inner $P:ident, $value:expr) => {
+        $P::try_unwrap($value).unwrap_or_else(|value| (*value).clone())
+    };
+}
+
+pointer_maskable!(Box,);
+pointer_maskable!(Rc, Clone);
+pointer_maskable!(Arc, Clone);
+
+/// The pointee of a `P` that is lifted into the root type by `parent`. `P` is a `Box`, an `Rc` or
+/// an `Arc`.
+pub struct PointerLift<L, P> {
+    parent: L,
+    _marker: PhantomData<fn() -> P>,
+}
+
+impl<L: Copy, P> Clone for PointerLift<L, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L: Copy, P> Copy for PointerLift<L, P> {}
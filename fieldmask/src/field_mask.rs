@@ -1,64 +1,422 @@
 use core::{
     convert::TryFrom,
+    fmt,
     ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
 };
+use std::borrow::Cow;
 
 use derive_more::{AsMut, AsRef, Deref, DerefMut, From};
 use thiserror::Error;
 
-use crate::maskable::{DeserializeMaskError, Maskable, SelfMaskable};
+use crate::maskable::{
+    DeserializeMaskError, DeserializeMaskErrorKind, DiffMaskable, Maskable, SelfMaskable,
+    SelfMaskableChanges, SelfMaskableRef,
+};
 
-#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct FieldMask<T: Maskable>(T::Mask);
 
 impl<T: Maskable> FieldMask<T> {
+    /// Wrap a raw mask, e.g. one taken out of a `FieldMask` of a type with the same mask.
+    pub const fn from_mask(mask: T::Mask) -> Self {
+        FieldMask(mask)
+    }
+
+    /// Unwrap the raw mask.
+    pub fn into_mask(self) -> T::Mask {
+        self.0
+    }
+
+    /// Include the field designated by `rhs`, whose segments are all unquoted.
     pub fn try_bitor_assign(&mut self, rhs: &[&str]) -> Result<(), DeserializeMaskError> {
-        T::try_bitor_assign_mask(&mut self.0, rhs)
+        let segs = rhs.iter().copied().map(Segment::from).collect::<Vec<_>>();
+        self.try_bitor_assign_segs(&segs)
+    }
+
+    /// Include the field designated by `segs`, e.g. as split by `parse_path`.
+    pub fn try_bitor_assign_segs(
+        &mut self,
+        segs: &[Segment<&str>],
+    ) -> Result<(), DeserializeMaskError> {
+        T::try_bitor_assign_mask(&mut self.0, segs)
+    }
+
+    /// Append the paths included in the mask to `paths`, each one prefixed with `prefix`.
+    pub fn append_paths(&self, prefix: &str, paths: &mut Vec<String>) -> Result<(), ToPathsError> {
+        T::append_mask_paths(&self.0, prefix, paths)
+    }
+
+    /// Serialize the mask into the minimal list of paths that selects the same fields.
+    ///
+    /// Paths can only add fields to a mask, so a mask that leaves out some of the fields under a
+    /// wildcard, e.g. one map key, can't be written as paths.
+    pub fn to_paths(&self) -> Result<Vec<String>, ToPathsError> {
+        let mut paths = Vec::new();
+        self.append_paths("", &mut paths)?;
+        Ok(paths)
+    }
+
+    pub fn contains_segs(&self, segs: &[Segment<&str>]) -> Result<bool, DeserializeMaskError> {
+        T::mask_contains(&self.0, segs)
+    }
+
+    pub fn intersects_segs(&self, segs: &[Segment<&str>]) -> Result<bool, DeserializeMaskError> {
+        T::mask_intersects(&self.0, segs)
+    }
+
+    /// Check whether everything under `path` is included in the mask.
+    pub fn contains(&self, path: &str) -> Result<bool, DeserializeFieldMaskError> {
+        with_path_segs(path, |segs| self.contains_segs(segs))
+    }
+
+    /// Check whether anything under `path` is included in the mask.
+    pub fn intersects(&self, path: &str) -> Result<bool, DeserializeFieldMaskError> {
+        with_path_segs(path, |segs| self.intersects_segs(segs))
+    }
+
+    /// Check whether the mask includes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.0 == T::Mask::default()
+    }
+
+    /// Check whether the mask includes everything.
+    pub fn is_full(&self) -> bool {
+        self.0 == !T::Mask::default()
+    }
+
+    /// Include a typed path, e.g. `Parent::paths().child_1().field_two()`, in the mask.
+    pub fn insert<P: Into<Self>>(&mut self, path: P)
+    where
+        T::Mask: BitOrAssign,
+    {
+        self.0 |= path.into().0;
     }
 }
 
+/// Parse `path` and call `f` with its segments.
+fn with_path_segs<R>(
+    path: &str,
+    f: impl FnOnce(&[Segment<&str>]) -> Result<R, DeserializeMaskError>,
+) -> Result<R, DeserializeFieldMaskError> {
+    parse_path(path)
+        .map_err(EntryError::from)
+        .and_then(|segs| {
+            f(&segs.iter().map(Segment::as_deref).collect::<Vec<_>>()).map_err(EntryError::from)
+        })
+        .map_err(|err| DeserializeFieldMaskError {
+            entry: path.into(),
+            err,
+        })
+}
+
 pub struct FieldMaskInput<T>(pub T);
 
 #[derive(Debug, Error)]
-#[error(r#"can not parse fieldmask "{entry}"; {err}"#)]
+#[error("{entry}: {err}")]
 pub struct DeserializeFieldMaskError {
     pub entry: String,
-    err: DeserializeMaskError,
+    err: EntryError,
+}
+
+/// Every invalid entry of a `FieldMaskInput`.
+#[derive(Debug, Error, Deref)]
+pub struct DeserializeFieldMaskErrors(Vec<DeserializeFieldMaskError>);
+
+impl fmt::Display for DeserializeFieldMaskErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, err) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for DeserializeFieldMaskErrors {
+    type Item = DeserializeFieldMaskError;
+    type IntoIter = std::vec::IntoIter<DeserializeFieldMaskError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl DeserializeFieldMaskError {
+    /// The error in the syntax of the path, if it couldn't be split into segments.
+    pub fn parse_error(&self) -> Option<&ParsePathError> {
+        match &self.err {
+            EntryError::Path(err) => Some(err),
+            EntryError::Mask(_) => None,
+        }
+    }
+
+    /// The error of the offending segment, if the path could be split into segments.
+    pub fn mask_error(&self) -> Option<&DeserializeMaskError> {
+        match &self.err {
+            EntryError::Path(_) => None,
+            EntryError::Mask(err) => Some(err),
+        }
+    }
+
+    /// The index of the offending segment in the path.
+    pub fn segment_index(&self) -> Option<usize> {
+        self.mask_error().map(|err| err.depth)
+    }
+
+    /// What's wrong with the offending segment.
+    pub fn kind(&self) -> Option<DeserializeMaskErrorKind> {
+        self.mask_error().map(|err| err.kind)
+    }
+}
+
+#[derive(Debug, Error)]
+enum EntryError {
+    #[error(transparent)]
+    Path(#[from] ParsePathError),
+    #[error(transparent)]
+    Mask(#[from] DeserializeMaskError),
+}
+
+/// The error of a mask that can't be written as paths.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error(r#"the mask of "{path}" leaves out some of the fields that no path can designate"#)]
+pub struct ToPathsError {
+    /// The path of the field whose mask can't be written.
+    pub path: String,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ParsePathError {
+    #[error("unterminated quoted segment starting at {position}")]
+    UnterminatedQuote { position: usize },
+    #[error("unexpected character after quoted segment at {position}")]
+    UnexpectedCharacter { position: usize },
+}
+
+/// A segment of a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Segment<S = String> {
+    /// A bare segment, e.g. `labels` or the `*` wildcard.
+    Unquoted(S),
+    /// A segment quoted with backticks, which is always taken literally, e.g. `` `*` `` is a key
+    /// named `*`.
+    Quoted(S),
+}
+
+impl<S: AsRef<str>> Segment<S> {
+    /// The segment without its quotes.
+    pub fn name(&self) -> &str {
+        match self {
+            Segment::Unquoted(name) | Segment::Quoted(name) => name.as_ref(),
+        }
+    }
+
+    pub fn as_deref(&self) -> Segment<&str> {
+        match self {
+            Segment::Unquoted(name) => Segment::Unquoted(name.as_ref()),
+            Segment::Quoted(name) => Segment::Quoted(name.as_ref()),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Segment<&'a str> {
+    fn from(name: &'a str) -> Self {
+        Segment::Unquoted(name)
+    }
+}
+
+/// Split a path into its segments.
+///
+/// Segments are separated by `.`. A segment can be quoted with backticks so that it can contain
+/// any character (e.g. ``labels.`example.com/team` ``), in which case a literal backtick is
+/// written as two backticks.
+pub fn parse_path(path: &str) -> Result<Vec<Segment>, ParsePathError> {
+    let mut segs = Vec::new();
+    let mut chars = path.char_indices().peekable();
+    loop {
+        let mut seg = String::new();
+        if let Some(&(start, '`')) = chars.peek() {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some((_, '`')) => match chars.peek() {
+                        Some((_, '`')) => {
+                            chars.next();
+                            seg.push('`');
+                        }
+                        _ => break,
+                    },
+                    Some((_, c)) => seg.push(c),
+                    None => return Err(ParsePathError::UnterminatedQuote { position: start }),
+                }
+            }
+            match chars.next() {
+                Some((_, '.')) => segs.push(Segment::Quoted(seg)),
+                Some((position, _)) => {
+                    return Err(ParsePathError::UnexpectedCharacter { position })
+                }
+                None => {
+                    segs.push(Segment::Quoted(seg));
+                    return Ok(segs);
+                }
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    Some((_, '.')) => break,
+                    Some((_, c)) => seg.push(c),
+                    None => {
+                        segs.push(Segment::Unquoted(seg));
+                        return Ok(segs);
+                    }
+                }
+            }
+            segs.push(Segment::Unquoted(seg));
+        }
+    }
+}
+
+/// Quote a segment with backticks if it contains anything other than ASCII letters, digits and
+/// underscores, so that `parse_path` gives it back as is and never as a wildcard.
+pub fn quote_segment(seg: &str) -> Cow<'_, str> {
+    if !seg.is_empty() && seg.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Cow::Borrowed(seg)
+    } else {
+        Cow::Owned(format!("`{}`", seg.replace('`', "``")))
+    }
+}
+
+/// Split `s` on every `sep` that is not inside a quoted segment.
+#[cfg(feature = "serde")]
+pub(crate) fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if c == '`' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            parts.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
 }
 
 impl<'a, I, T> TryFrom<FieldMaskInput<I>> for FieldMask<T>
 where
     I: Iterator<Item = &'a str>,
     T: Maskable,
-    T::Mask: Default,
 {
     type Error = DeserializeFieldMaskError;
 
     fn try_from(value: FieldMaskInput<I>) -> Result<Self, Self::Error> {
         let mut mask = Self::default();
         for entry in value.0 {
-            mask.try_bitor_assign(&entry.split('.').collect::<Vec<_>>())
-                .map_err(|err| DeserializeFieldMaskError {
-                    entry: entry.into(),
-                    err,
-                })?;
+            with_path_segs(entry, |segs| mask.try_bitor_assign_segs(segs))?;
         }
         Ok(mask)
     }
 }
 
+impl<T: Maskable> FieldMask<T> {
+    /// Same as `try_from`, but reports every invalid entry instead of only the first one.
+    pub fn try_from_all<'a, I>(value: FieldMaskInput<I>) -> Result<Self, DeserializeFieldMaskErrors>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let (mask, errors) = Self::from_valid_entries(value);
+        if errors.is_empty() {
+            Ok(mask)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Same as `try_from`, but entries with an unknown field are skipped and returned as warnings
+    /// instead, e.g. for paths sent by a client built against a newer version of `T`. Every other
+    /// invalid entry is still an error.
+    pub fn try_from_lenient<'a, I>(
+        value: FieldMaskInput<I>,
+    ) -> Result<(Self, DeserializeFieldMaskErrors), DeserializeFieldMaskError>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut mask = Self::default();
+        let mut warnings = Vec::new();
+        for entry in value.0 {
+            match with_path_segs(entry, |segs| mask.try_bitor_assign_segs(segs)) {
+                Err(err) if err.kind() == Some(DeserializeMaskErrorKind::UnknownField) => {
+                    warnings.push(err)
+                }
+                res => res?,
+            }
+        }
+        Ok((mask, DeserializeFieldMaskErrors(warnings)))
+    }
+
+    /// Build a mask from the valid entries, and report the invalid ones.
+    pub fn from_valid_entries<'a, I>(value: FieldMaskInput<I>) -> (Self, DeserializeFieldMaskErrors)
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut mask = Self::default();
+        // A failed `try_bitor_assign` leaves the mask untouched, so the invalid entries are
+        // simply skipped.
+        let errors = value
+            .0
+            .filter_map(|entry| {
+                with_path_segs(entry, |segs| mask.try_bitor_assign_segs(segs)).err()
+            })
+            .collect();
+        (mask, DeserializeFieldMaskErrors(errors))
+    }
+}
+
 impl<T: SelfMaskable> FieldMask<T> {
     /// Update the object according to mask.
     pub fn apply(self, target: &mut T, src: T) {
         T::apply_mask(target, src, self.0);
     }
+
+    /// Reset every field of `value` that is not included in the mask to its default value.
+    pub fn project(&self, mut value: T) -> T {
+        self.project_in_place(&mut value);
+        value
+    }
+
+    /// Same as `project`, but modifies `target` in place.
+    pub fn project_in_place(&self, target: &mut T) {
+        T::project_mask(target, &self.0);
+    }
 }
 
-impl<T> BitAnd for FieldMask<T>
-where
-    T: Maskable,
-    T::Mask: BitAnd<Output = T::Mask>,
-{
+impl<T: SelfMaskableRef> FieldMask<T> {
+    /// Same as `apply`, but borrows `src` and only clones the fields included in the mask.
+    pub fn apply_ref(&self, target: &mut T, src: &T) {
+        T::apply_mask_ref(target, src, &self.0);
+    }
+}
+
+impl<T: SelfMaskableChanges> FieldMask<T> {
+    /// Same as `apply`, but returns the mask of the leaves whose values changed. It's the same
+    /// as the `diff` between `target` before and after the application.
+    pub fn apply_with_changes(self, target: &mut T, src: T) -> Self {
+        FieldMask(T::apply_mask_with_changes(target, src, self.0))
+    }
+}
+
+impl<T: DiffMaskable> FieldMask<T> {
+    /// Compare two objects and build the minimal mask that includes every leaf that is different.
+    /// Applying the mask to `old` with `new` as the source gives back `new`.
+    pub fn diff(old: &T, new: &T) -> Self {
+        FieldMask(old.diff_mask(new))
+    }
+}
+
+impl<T: Maskable> BitAnd for FieldMask<T> {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
@@ -75,10 +433,7 @@ where
     }
 }
 
-impl<T: Maskable> BitOr for FieldMask<T>
-where
-    T::Mask: BitOr<Output = T::Mask>,
-{
+impl<T: Maskable> BitOr for FieldMask<T> {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
@@ -95,10 +450,7 @@ where
     }
 }
 
-impl<T: Maskable> BitXor for FieldMask<T>
-where
-    T::Mask: BitXor<Output = T::Mask>,
-{
+impl<T: Maskable> BitXor for FieldMask<T> {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
@@ -115,10 +467,7 @@ where
     }
 }
 
-impl<T: Maskable> Not for FieldMask<T>
-where
-    T::Mask: Not<Output = T::Mask>,
-{
+impl<T: Maskable> Not for FieldMask<T> {
     type Output = Self;
 
     fn not(self) -> Self::Output {
@@ -126,16 +475,114 @@ where
     }
 }
 
-impl<T: Maskable> Default for FieldMask<T>
-where
-    T::Mask: Default,
-{
+// These traits are implemented by hand, because deriving them would require `T` itself to
+// implement them instead of only `T::Mask`.
+impl<T: Maskable> Clone for FieldMask<T> {
+    fn clone(&self) -> Self {
+        FieldMask(self.0.clone())
+    }
+}
+
+impl<T: Maskable> Copy for FieldMask<T> where T::Mask: Copy {}
+
+impl<T: Maskable> PartialEq for FieldMask<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Maskable> fmt::Debug for FieldMask<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FieldMask").field(&self.0).finish()
+    }
+}
+
+impl<T: Maskable> AsRef<T::Mask> for FieldMask<T> {
+    fn as_ref(&self) -> &T::Mask {
+        &self.0
+    }
+}
+
+impl<T: Maskable> AsMut<T::Mask> for FieldMask<T> {
+    fn as_mut(&mut self) -> &mut T::Mask {
+        &mut self.0
+    }
+}
+
+impl<T: Maskable> Default for FieldMask<T> {
     fn default() -> Self {
         FieldMask(T::Mask::default())
     }
 }
 
-#[derive(AsMut, AsRef, Deref, DerefMut, From, Default, PartialEq, Eq, Debug)]
+/// The mutable mask of a field, as returned by the mutable accessors of the masks of derived
+/// types.
+///
+/// The mask of a field packed in a bit by `#[fieldmask(packed)]` is a copy, which is written back
+/// when it's dropped.
+pub struct FieldMaskMut<'a, T: Maskable>(FieldMaskMutInner<'a, T>);
+
+enum FieldMaskMutInner<'a, T: Maskable> {
+    Field(&'a mut FieldMask<T>),
+    Bit {
+        word: &'a mut u64,
+        bit: usize,
+        mask: FieldMask<T>,
+    },
+}
+
+impl<'a, T: Maskable> FieldMaskMut<'a, T> {
+    /// The mask of the field stored in `bit` of `word`, which is set if the mask isn't empty.
+    pub(crate) fn bit(word: &'a mut u64, bit: usize, mask: FieldMask<T>) -> Self {
+        FieldMaskMut(FieldMaskMutInner::Bit { word, bit, mask })
+    }
+}
+
+impl<'a, T: Maskable> From<&'a mut FieldMask<T>> for FieldMaskMut<'a, T> {
+    fn from(mask: &'a mut FieldMask<T>) -> Self {
+        FieldMaskMut(FieldMaskMutInner::Field(mask))
+    }
+}
+
+impl<'a, T: Maskable> core::ops::Deref for FieldMaskMut<'a, T> {
+    type Target = FieldMask<T>;
+
+    fn deref(&self) -> &FieldMask<T> {
+        match &self.0 {
+            FieldMaskMutInner::Field(mask) => mask,
+            FieldMaskMutInner::Bit { mask, .. } => mask,
+        }
+    }
+}
+
+impl<'a, T: Maskable> core::ops::DerefMut for FieldMaskMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut FieldMask<T> {
+        match &mut self.0 {
+            FieldMaskMutInner::Field(mask) => mask,
+            FieldMaskMutInner::Bit { mask, .. } => mask,
+        }
+    }
+}
+
+impl<'a, T: Maskable> Drop for FieldMaskMut<'a, T> {
+    fn drop(&mut self) {
+        if let FieldMaskMutInner::Bit { word, bit, mask } = &mut self.0 {
+            if *mask == FieldMask::default() {
+                **word &= !(1 << *bit);
+            } else {
+                **word |= 1 << *bit;
+            }
+        }
+    }
+}
+
+impl<'a, T: Maskable> fmt::Debug for FieldMaskMut<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[derive(AsMut, AsRef, Deref, DerefMut, From, Clone, Copy, Default, PartialEq, Eq, Debug)]
 #[deref(forward)]
 #[deref_mut(forward)]
 pub struct BitwiseWrap<T>(pub T);
@@ -0,0 +1,207 @@
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use crate::{
+    field_mask::{FieldMask, FieldMaskMut},
+    maskable::Maskable,
+};
+
+/// The masks of up to 64 leaf fields, one bit per field.
+///
+/// `#[fieldmask(packed)]` stores the masks of the leaf fields of a type in `LeafMask`s instead of
+/// one `FieldMask` per field, which makes its `Mask` smaller and cheaper to combine. Every field
+/// has a bit, and `LEAVES` has the ones of the leaf fields set.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct LeafMask<const LEAVES: u64>(u64);
+
+impl<const LEAVES: u64> LeafMask<LEAVES> {
+    pub fn get(self, bit: usize) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    pub fn set(&mut self, bit: usize, value: bool) {
+        if value {
+            self.0 |= 1 << bit;
+        } else {
+            self.0 &= !(1 << bit);
+        }
+    }
+}
+
+impl<const LEAVES: u64> BitAnd for LeafMask<LEAVES> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        LeafMask(self.0 & rhs.0)
+    }
+}
+
+impl<const LEAVES: u64> BitAndAssign for LeafMask<LEAVES> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl<const LEAVES: u64> BitOr for LeafMask<LEAVES> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        LeafMask(self.0 | rhs.0)
+    }
+}
+
+impl<const LEAVES: u64> BitOrAssign for LeafMask<LEAVES> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl<const LEAVES: u64> BitXor for LeafMask<LEAVES> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        LeafMask(self.0 ^ rhs.0)
+    }
+}
+
+impl<const LEAVES: u64> BitXorAssign for LeafMask<LEAVES> {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl<const LEAVES: u64> Not for LeafMask<LEAVES> {
+    type Output = Self;
+
+    // Only the bits of the leaf fields are flipped, so that a full mask is the same however it
+    // was built.
+    fn not(self) -> Self::Output {
+        LeafMask(!self.0 & LEAVES)
+    }
+}
+
+/// The part of the mask of a packed type that belongs to a leaf field, which is nothing since its
+/// mask is a bit of a `LeafMask`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct InLeafMask;
+
+impl BitAnd for InLeafMask {
+    type Output = Self;
+
+    fn bitand(self, _rhs: Self) -> Self::Output {
+        self
+    }
+}
+
+impl BitAndAssign for InLeafMask {
+    fn bitand_assign(&mut self, _rhs: Self) {}
+}
+
+impl BitOr for InLeafMask {
+    type Output = Self;
+
+    fn bitor(self, _rhs: Self) -> Self::Output {
+        self
+    }
+}
+
+impl BitOrAssign for InLeafMask {
+    fn bitor_assign(&mut self, _rhs: Self) {}
+}
+
+impl BitXor for InLeafMask {
+    type Output = Self;
+
+    fn bitxor(self, _rhs: Self) -> Self::Output {
+        self
+    }
+}
+
+impl BitXorAssign for InLeafMask {
+    fn bitxor_assign(&mut self, _rhs: Self) {}
+}
+
+impl Not for InLeafMask {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        self
+    }
+}
+
+/// Selects where a packed type stores the mask of a field of type `T`, given `T::LEAF`.
+pub struct Packed<const LEAF: bool>;
+
+pub trait PackedStorage<T: Maskable> {
+    type Storage: PackedField<T>;
+}
+
+impl<T: Maskable<Mask = bool>> PackedStorage<T> for Packed<true> {
+    type Storage = InLeafMask;
+}
+
+impl<T: Maskable> PackedStorage<T> for Packed<false> {
+    type Storage = FieldMask<T>;
+}
+
+/// Access to the mask of a field of type `T` in a packed type, which is either stored as is or in
+/// `bit` of `word`.
+pub trait PackedField<T: Maskable>: Sized {
+    fn get<const LEAVES: u64>(&self, word: &LeafMask<LEAVES>, bit: usize) -> &FieldMask<T>;
+
+    fn get_mut<'a, const LEAVES: u64>(
+        &'a mut self,
+        word: &'a mut LeafMask<LEAVES>,
+        bit: usize,
+    ) -> FieldMaskMut<'a, T>;
+
+    fn take<const LEAVES: u64>(self, word: &LeafMask<LEAVES>, bit: usize) -> FieldMask<T>;
+}
+
+impl<T: Maskable> PackedField<T> for FieldMask<T> {
+    fn get<const LEAVES: u64>(&self, _word: &LeafMask<LEAVES>, _bit: usize) -> &FieldMask<T> {
+        self
+    }
+
+    fn get_mut<'a, const LEAVES: u64>(
+        &'a mut self,
+        _word: &'a mut LeafMask<LEAVES>,
+        _bit: usize,
+    ) -> FieldMaskMut<'a, T> {
+        FieldMaskMut::from(self)
+    }
+
+    fn take<const LEAVES: u64>(self, _word: &LeafMask<LEAVES>, _bit: usize) -> FieldMask<T> {
+        self
+    }
+}
+
+// A bit can't be borrowed as a `FieldMask`, so `get` borrows one of these constants instead.
+struct LeafMasks<T>(T);
+
+impl<T: Maskable<Mask = bool>> LeafMasks<T> {
+    const EMPTY: FieldMask<T> = FieldMask::from_mask(false);
+    const FULL: FieldMask<T> = FieldMask::from_mask(true);
+}
+
+impl<T: Maskable<Mask = bool>> PackedField<T> for InLeafMask {
+    fn get<const LEAVES: u64>(&self, word: &LeafMask<LEAVES>, bit: usize) -> &FieldMask<T> {
+        if word.get(bit) {
+            &LeafMasks::<T>::FULL
+        } else {
+            &LeafMasks::<T>::EMPTY
+        }
+    }
+
+    fn get_mut<'a, const LEAVES: u64>(
+        &'a mut self,
+        word: &'a mut LeafMask<LEAVES>,
+        bit: usize,
+    ) -> FieldMaskMut<'a, T> {
+        let mask = FieldMask::from_mask(word.get(bit));
+        FieldMaskMut::bit(&mut word.0, bit, mask)
+    }
+
+    fn take<const LEAVES: u64>(self, word: &LeafMask<LEAVES>, bit: usize) -> FieldMask<T> {
+        FieldMask::from_mask(word.get(bit))
+    }
+}
@@ -1,6 +1,27 @@
-pub use field_mask::{BitwiseWrap, DeserializeFieldMaskError, FieldMask, FieldMaskInput};
+pub use boxed::{BoxMask, PointerLift};
+pub use field_mask::{
+    parse_path, quote_segment, BitwiseWrap, DeserializeFieldMaskError, DeserializeFieldMaskErrors,
+    FieldMask, FieldMaskInput, FieldMaskMut, ParsePathError, Segment, ToPathsError,
+};
 pub use fieldmask_derive::Maskable;
-pub use maskable::{DeserializeMaskError, Maskable, OptionMaskable, SelfMaskable};
+pub use leaf::{InLeafMask, LeafMask, Packed, PackedField, PackedStorage};
+pub use map::MapMask;
+pub use maskable::{
+    DeserializeMaskError, DeserializeMaskErrorKind, DiffMaskable, Maskable, OptionMaskable,
+    OptionMaskableChanges, OptionMaskableRef, PathInfo, SelfMaskable, SelfMaskableChanges,
+    SelfMaskableRef,
+};
+pub use path::{FieldLift, Flattened, LeafPath, Lift, MaskablePaths, OptionLift, RootLift};
+pub use vec::{ElementLift, Elements, VecMask, VecPath};
 
+mod boxed;
 mod field_mask;
+mod leaf;
+mod map;
 mod maskable;
+mod path;
+#[cfg(feature = "prost-integration")]
+mod prost_integration;
+#[cfg(feature = "serde")]
+mod serde_integration;
+mod vec;
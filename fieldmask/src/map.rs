@@ -0,0 +1,489 @@
+use core::{
+    fmt::{Debug, Display},
+    hash::{BuildHasher, Hash},
+    mem,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+    str::FromStr,
+};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    field_mask::{quote_segment, Segment, ToPathsError},
+    maskable::{
+        join_path, DeserializeMaskError, DiffMaskable, Maskable, OptionMaskable,
+        OptionMaskableChanges, OptionMaskableRef, PathInfo, SelfMaskable, SelfMaskableChanges,
+        SelfMaskableRef,
+    },
+};
+
+/// Mask of a map field.
+///
+/// Every key can have its own mask. Keys without one are either fully included or fully
+/// excluded, depending on `rest`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MapMask<K, M> {
+    rest: bool,
+    entries: BTreeMap<K, M>,
+}
+
+impl<K, M> Default for MapMask<K, M> {
+    fn default() -> Self {
+        MapMask {
+            rest: false,
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord, M> MapMask<K, M>
+where
+    M: Default + Not<Output = M> + PartialEq,
+{
+    fn rest_mask(rest: bool) -> M {
+        if rest {
+            !M::default()
+        } else {
+            M::default()
+        }
+    }
+
+    /// The mask of the value of `key`.
+    pub fn get(&self, key: &K) -> M
+    where
+        M: Clone,
+    {
+        self.entries
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| Self::rest_mask(self.rest))
+    }
+
+    /// Combine two map masks key by key, using `rest` of either side for the keys it has no entry
+    /// for. A key whose mask ends up all or nothing like the keys without an entry loses its own,
+    /// since the derived `PartialEq` compares the entries.
+    fn merge(self, rhs: Self, rest: bool, f: impl Fn(M, M) -> M) -> Self {
+        let MapMask {
+            rest: lhs_rest,
+            entries: lhs_entries,
+        } = self;
+        let MapMask {
+            rest: rhs_rest,
+            entries: mut rhs_entries,
+        } = rhs;
+        let mut entries = BTreeMap::new();
+        for (key, lhs) in lhs_entries {
+            let rhs = rhs_entries
+                .remove(&key)
+                .unwrap_or_else(|| Self::rest_mask(rhs_rest));
+            entries.insert(key, f(lhs, rhs));
+        }
+        for (key, rhs) in rhs_entries {
+            entries.insert(key, f(Self::rest_mask(lhs_rest), rhs));
+        }
+        let rest_mask = Self::rest_mask(rest);
+        entries.retain(|_, mask| *mask != rest_mask);
+        MapMask { rest, entries }
+    }
+}
+
+impl<K: Ord, M> BitAnd for MapMask<K, M>
+where
+    M: BitAnd<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let rest = self.rest & rhs.rest;
+        self.merge(rhs, rest, |l, r| l & r)
+    }
+}
+
+impl<K: Ord, M> BitAndAssign for MapMask<K, M>
+where
+    M: BitAnd<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) & rhs;
+    }
+}
+
+impl<K: Ord, M> BitOr for MapMask<K, M>
+where
+    M: BitOr<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let rest = self.rest | rhs.rest;
+        self.merge(rhs, rest, |l, r| l | r)
+    }
+}
+
+impl<K: Ord, M> BitOrAssign for MapMask<K, M>
+where
+    M: BitOr<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) | rhs;
+    }
+}
+
+impl<K: Ord, M> BitXor for MapMask<K, M>
+where
+    M: BitXor<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let rest = self.rest ^ rhs.rest;
+        self.merge(rhs, rest, |l, r| l ^ r)
+    }
+}
+
+impl<K: Ord, M> BitXorAssign for MapMask<K, M>
+where
+    M: BitXor<Output = M> + Default + Not<Output = M> + PartialEq,
+{
+    fn bitxor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) ^ rhs;
+    }
+}
+
+impl<K: Ord, M: Not<Output = M>> Not for MapMask<K, M> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        MapMask {
+            rest: !self.rest,
+            entries: self
+                .entries
+                .into_iter()
+                .map(|(key, mask)| (key, !mask))
+                .collect(),
+        }
+    }
+}
+
+/// Parse a key. A wildcard can only be the last segment of a path, so an unquoted `*` followed
+/// by other segments is rejected rather than taken as a key, and so is an unquoted empty segment
+/// like the one in `labels.`. Both can be quoted to be used as keys.
+fn parse_key<K: FromStr>(
+    type_str: &'static str,
+    key: &Segment<&str>,
+) -> Result<K, DeserializeMaskError> {
+    let err = || DeserializeMaskError::invalid_key(type_str, *key);
+    if let Segment::Unquoted("*" | "") = key {
+        return Err(err());
+    }
+    key.name().parse().map_err(|_| err())
+}
+
+// The keys are `Clone` and `Debug` because `Maskable::Mask` is, so maps with other keys are not
+// `Maskable` anymore.
+macro_rules! map_maskable {
+    ($T:ident<K, V $(, $S:ident)?> where K: $($K_bound:path),+ $(; $S_bound:path)?) => {
+        impl<K, V $(, $S)?> Maskable for $T<K, V $(, $S)?>
+        where
+            K: Ord + Clone + Debug + FromStr + Display $(+ $K_bound)+,
+            V: Maskable,
+            $($S: $S_bound,)?
+        {
+            type Mask = MapMask<K, V::Mask>;
+
+            const TYPE_STR: &'static str = stringify!($T);
+
+            fn try_bitor_assign_mask(
+                mask: &mut Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<(), DeserializeMaskError> {
+                match field_mask_segs {
+                    [] | [Segment::Unquoted("*")] => *mask = !MapMask::default(),
+                    [key, tail @ ..] => {
+                        let key = parse_key(stringify!($T), key)?;
+                        let mut entry = V::Mask::default();
+                        V::try_bitor_assign_mask(&mut entry, tail).map_err(|mut e| {
+                            e.depth += 1;
+                            e
+                        })?;
+                        let mut entries = BTreeMap::new();
+                        entries.insert(key, entry);
+                        *mask |= MapMask {
+                            rest: false,
+                            entries,
+                        };
+                    }
+                }
+                Ok(())
+            }
+
+            /// A mask that includes every key except some of their fields can't be written as
+            /// paths, since `*` includes all of them.
+            fn append_mask_paths(
+                mask: &Self::Mask,
+                prefix: &str,
+                paths: &mut Vec<String>,
+            ) -> Result<(), ToPathsError> {
+                if mask.rest {
+                    if !mask.entries.is_empty() {
+                        return Err(ToPathsError {
+                            path: prefix.into(),
+                        });
+                    }
+                    paths.push(prefix.into());
+                    return Ok(());
+                }
+                for (key, entry) in &mask.entries {
+                    let key = key.to_string();
+                    let path = join_path(prefix, &quote_segment(&key));
+                    if *entry == !V::Mask::default() {
+                        paths.push(path);
+                    } else {
+                        V::append_mask_paths(entry, &path, paths)?;
+                    }
+                }
+                Ok(())
+            }
+
+            fn mask_contains(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                match field_mask_segs {
+                    [] | [Segment::Unquoted("*")] => Ok(mask.rest && mask.entries.is_empty()),
+                    [key, tail @ ..] => {
+                        let key = parse_key(stringify!($T), key)?;
+                        let rest_mask = MapMask::<K, V::Mask>::rest_mask(mask.rest);
+                        V::mask_contains(mask.entries.get(&key).unwrap_or(&rest_mask), tail)
+                            .map_err(|mut e| {
+                                e.depth += 1;
+                                e
+                            })
+                    }
+                }
+            }
+
+            fn mask_intersects(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                match field_mask_segs {
+                    [] | [Segment::Unquoted("*")] => Ok(mask.rest || !mask.entries.is_empty()),
+                    [key, tail @ ..] => {
+                        let key = parse_key(stringify!($T), key)?;
+                        let rest_mask = MapMask::<K, V::Mask>::rest_mask(mask.rest);
+                        V::mask_intersects(mask.entries.get(&key).unwrap_or(&rest_mask), tail)
+                            .map_err(|mut e| {
+                                e.depth += 1;
+                                e
+                            })
+                    }
+                }
+            }
+
+            /// `*` can only be the last segment of a path to a map, so the paths below the
+            /// values are not listed.
+            fn append_all_paths(prefix: &str, paths: &mut Vec<PathInfo>) {
+                paths.push(PathInfo {
+                    path: join_path(prefix, "*"),
+                    type_str: V::TYPE_STR,
+                });
+            }
+
+            fn append_json_segs<'a>(
+                field_mask_segs: &[Segment<&'a str>],
+                to_json: bool,
+                renamed: &mut Vec<Segment<&'a str>>,
+            ) -> bool {
+                if let [key, tail @ ..] = field_mask_segs {
+                    renamed.push(*key);
+                    V::append_json_segs(tail, to_json, renamed);
+                }
+                true
+            }
+        }
+
+        impl<K, V $(, $S)?> SelfMaskable for $T<K, V $(, $S)?>
+        where
+            K: Ord + Clone + Debug + FromStr + Display $(+ $K_bound)+,
+            V: OptionMaskable + Default,
+            $($S: $S_bound,)?
+        {
+            fn apply_mask(&mut self, src: Self, mask: Self::Mask) {
+                let MapMask { rest, mut entries } = mask;
+                if rest {
+                    // Keys that are neither in `src` nor in `entries` are fully masked and missing
+                    // from `src`, so they are removed.
+                    self.retain(|key, _| src.contains_key(key) || entries.contains_key(key));
+                }
+                for (key, value) in src {
+                    let mask = entries
+                        .remove(&key)
+                        .unwrap_or_else(|| MapMask::<K, V::Mask>::rest_mask(rest));
+                    if mask == V::Mask::default() {
+                        continue;
+                    }
+                    match self.get_mut(&key) {
+                        Some(s) => {
+                            if !s.apply_mask(value, mask) {
+                                self.remove(&key);
+                            }
+                        }
+                        None => {
+                            let mut new = V::default();
+                            if new.apply_mask(value, mask) {
+                                self.insert(key, new);
+                            }
+                        }
+                    }
+                }
+                // The remaining keys are masked but missing from `src`.
+                for (key, mask) in entries {
+                    if mask != V::Mask::default() {
+                        self.remove(&key);
+                    }
+                }
+            }
+
+            fn project_mask(&mut self, mask: &Self::Mask) {
+                let rest_mask = MapMask::<K, V::Mask>::rest_mask(mask.rest);
+                self.retain(|key, value| {
+                    let mask = mask.entries.get(key).unwrap_or(&rest_mask);
+                    *mask != V::Mask::default() && value.project_mask(mask)
+                });
+            }
+        }
+
+        impl<K, V $(, $S)?> SelfMaskableRef for $T<K, V $(, $S)?>
+        where
+            K: Ord + Clone + Debug + FromStr + Display $(+ $K_bound)+,
+            V: OptionMaskableRef + Default,
+            $($S: $S_bound,)?
+        {
+            fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) {
+                let rest_mask = MapMask::<K, V::Mask>::rest_mask(mask.rest);
+                if mask.rest {
+                    self.retain(|key, _| src.contains_key(key) || mask.entries.contains_key(key));
+                }
+                for (key, value) in src {
+                    let mask = mask.entries.get(key).unwrap_or(&rest_mask);
+                    if *mask == V::Mask::default() {
+                        continue;
+                    }
+                    match self.get_mut(key) {
+                        Some(s) => {
+                            if !s.apply_mask_ref(value, mask) {
+                                self.remove(key);
+                            }
+                        }
+                        None => {
+                            let mut new = V::default();
+                            if new.apply_mask_ref(value, mask) {
+                                self.insert(key.clone(), new);
+                            }
+                        }
+                    }
+                }
+                for (key, mask) in &mask.entries {
+                    if *mask != V::Mask::default() && !src.contains_key(key) {
+                        self.remove(key);
+                    }
+                }
+            }
+        }
+
+        impl<K, V $(, $S)?> SelfMaskableChanges for $T<K, V $(, $S)?>
+        where
+            K: Ord + Clone + Debug + FromStr + Display $(+ $K_bound)+,
+            V: OptionMaskableChanges + Default,
+            $($S: $S_bound,)?
+        {
+            /// Adding or removing a key changes all of its value.
+            fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> Self::Mask {
+                let MapMask { rest, mut entries } = mask;
+                let mut changes = BTreeMap::new();
+                if rest {
+                    let removed = self
+                        .keys()
+                        .filter(|key| !src.contains_key(*key) && !entries.contains_key(*key))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for key in removed {
+                        self.remove(&key);
+                        changes.insert(key, !V::Mask::default());
+                    }
+                }
+                for (key, value) in src {
+                    let mask = entries
+                        .remove(&key)
+                        .unwrap_or_else(|| MapMask::<K, V::Mask>::rest_mask(rest));
+                    if mask == V::Mask::default() {
+                        continue;
+                    }
+                    let entry_changes = match self.get_mut(&key) {
+                        Some(s) => {
+                            let (keep, entry_changes) = s.apply_mask_with_changes(value, mask);
+                            if keep {
+                                entry_changes
+                            } else {
+                                self.remove(&key);
+                                !V::Mask::default()
+                            }
+                        }
+                        None => {
+                            let mut new = V::default();
+                            if !new.apply_mask(value, mask) {
+                                continue;
+                            }
+                            self.insert(key.clone(), new);
+                            !V::Mask::default()
+                        }
+                    };
+                    if entry_changes != V::Mask::default() {
+                        changes.insert(key, entry_changes);
+                    }
+                }
+                for (key, mask) in entries {
+                    if mask != V::Mask::default() && self.remove(&key).is_some() {
+                        changes.insert(key, !V::Mask::default());
+                    }
+                }
+                MapMask {
+                    rest: false,
+                    entries: changes,
+                }
+            }
+        }
+
+        impl<K, V $(, $S)?> DiffMaskable for $T<K, V $(, $S)?>
+        where
+            K: Ord + Clone + Debug + FromStr + Display $(+ $K_bound)+,
+            V: DiffMaskable + Default,
+            $($S: $S_bound,)?
+        {
+            fn diff_mask(&self, other: &Self) -> Self::Mask {
+                let mut entries = BTreeMap::new();
+                for (key, s) in self {
+                    let mask = match other.get(key) {
+                        Some(o) => s.diff_mask(o),
+                        None => !V::Mask::default(),
+                    };
+                    if mask != V::Mask::default() {
+                        entries.insert(key.clone(), mask);
+                    }
+                }
+                for key in other.keys() {
+                    if !self.contains_key(key) {
+                        entries.insert(key.clone(), !V::Mask::default());
+                    }
+                }
+                MapMask {
+                    rest: false,
+                    entries,
+                }
+            }
+        }
+    };
+}
+
+map_maskable!(HashMap<K, V, S> where K: Hash, Eq; BuildHasher);
+map_maskable!(BTreeMap<K, V> where K: Ord);
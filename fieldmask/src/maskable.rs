@@ -1,19 +1,236 @@
+use core::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Not},
+};
+
 use thiserror::Error;
 
-#[derive(Debug, Error)]
-#[error(r#"there's no "{field}" in `{type_str}`"#)]
+use crate::{
+    field_mask::{parse_path, Segment, ToPathsError},
+    path::leaf_paths,
+};
+
+#[derive(Debug, Error, Clone)]
 pub struct DeserializeMaskError {
+    /// The type in which the segment was looked up.
     pub type_str: &'static str,
+    /// The offending segment.
     pub field: String,
-    pub depth: u8,
+    /// The index of the offending segment in the path given to `try_bitor_assign_mask`. Every
+    /// type that passes the tail of the path to a field bumps it on the way back.
+    pub depth: usize,
+    pub kind: DeserializeMaskErrorKind,
+    /// Append the fields of `type_str` to the given names, which are only listed when they are
+    /// asked for.
+    append_candidates: Option<fn(&mut Vec<&'static str>)>,
+}
+
+// The candidates are left out, since they follow from `type_str` and function pointers can't be
+// compared reliably.
+impl PartialEq for DeserializeMaskError {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_str == other.type_str
+            && self.field == other.field
+            && self.depth == other.depth
+            && self.kind == other.kind
+    }
+}
+
+impl Eq for DeserializeMaskError {}
+
+/// What's wrong with the offending segment of a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeserializeMaskErrorKind {
+    /// The type has no field with this name.
+    UnknownField,
+    /// The type is a leaf, e.g. a `String`, so it has no fields at all.
+    BelowLeaf,
+    /// The segment is not a valid index of a vector, e.g. `first` in `items.first`.
+    InvalidIndex,
+    /// The segment is not a valid key of a map, e.g. `x` in `ports.x` for integer keys.
+    InvalidKey,
+    /// The segment is empty, e.g. the second one in `child_1..field_two`.
+    EmptySegment,
+    /// The segment is a `*` that is not the last one and doesn't stand for the elements of a
+    /// vector or a map.
+    InvalidWildcard,
 }
 
+impl DeserializeMaskError {
+    /// The error of a segment that is not a field of `type_str`.
+    pub fn unknown_field(type_str: &'static str, seg: Segment<&str>) -> Self {
+        Self::new(type_str, seg, DeserializeMaskErrorKind::UnknownField)
+    }
+
+    /// The error of a segment that can't be parsed as an index of `type_str`.
+    pub fn invalid_index(type_str: &'static str, seg: Segment<&str>) -> Self {
+        Self::new(type_str, seg, DeserializeMaskErrorKind::InvalidIndex)
+    }
+
+    /// The error of a segment that can't be parsed as a key of `type_str`.
+    pub fn invalid_key(type_str: &'static str, seg: Segment<&str>) -> Self {
+        Self::new(type_str, seg, DeserializeMaskErrorKind::InvalidKey)
+    }
+
+    /// The error of a segment below `type_str`, which has no fields.
+    pub fn below_leaf(type_str: &'static str, seg: Segment<&str>) -> Self {
+        Self::new(type_str, seg, DeserializeMaskErrorKind::BelowLeaf)
+    }
+
+    /// Unquoted empty segments and wildcards are never fields, so they are reported as such
+    /// whatever `kind` is.
+    fn new(type_str: &'static str, seg: Segment<&str>, kind: DeserializeMaskErrorKind) -> Self {
+        let kind = match seg {
+            Segment::Unquoted("") => DeserializeMaskErrorKind::EmptySegment,
+            Segment::Unquoted("*") => DeserializeMaskErrorKind::InvalidWildcard,
+            _ => kind,
+        };
+        DeserializeMaskError {
+            type_str,
+            field: seg.name().into(),
+            depth: 0,
+            kind,
+            append_candidates: None,
+        }
+    }
+
+    /// Set how to list the fields that `field` could have been, usually
+    /// `Maskable::append_field_names` of `type_str`.
+    pub fn with_candidates(mut self, append_candidates: fn(&mut Vec<&'static str>)) -> Self {
+        self.append_candidates = Some(append_candidates);
+        self
+    }
+
+    /// The fields that `field` could have been, the closest ones first.
+    pub fn candidates(&self) -> Vec<&'static str> {
+        let mut candidates = Vec::new();
+        if let Some(append_candidates) = self.append_candidates {
+            append_candidates(&mut candidates);
+        }
+        candidates.sort_by_cached_key(|candidate| edit_distance(&self.field, candidate));
+        candidates
+    }
+
+    /// The closest candidate, if it's close enough to be what was meant.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        let candidate = *self.candidates().first()?;
+        let max_distance = self.field.chars().count().max(3) / 3;
+        if edit_distance(&self.field, candidate) <= max_distance {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+}
+
+/// The optimal string alignment distance between `a` and `b`: the number of insertions,
+/// deletions, substitutions and transpositions of adjacent characters that turn one into the
+/// other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // `rows[i][j]` is the distance between the first `i` characters of `a` and the first `j`
+    // characters of `b`.
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in rows[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+impl fmt::Display for DeserializeMaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let DeserializeMaskError {
+            type_str, field, ..
+        } = self;
+        match self.kind {
+            DeserializeMaskErrorKind::UnknownField => {
+                write!(f, r#"no field "{}" in {}"#, field, type_str)?;
+                match self.suggestion() {
+                    Some(suggestion) => write!(f, r#"; did you mean "{}"?"#, suggestion),
+                    None => Ok(()),
+                }
+            }
+            DeserializeMaskErrorKind::BelowLeaf => write!(
+                f,
+                r#"no field "{}" in {}, which has no fields"#,
+                field, type_str
+            ),
+            DeserializeMaskErrorKind::InvalidIndex => {
+                write!(f, r#"invalid index "{}" in {}"#, field, type_str)
+            }
+            DeserializeMaskErrorKind::InvalidKey => {
+                write!(f, r#"invalid key "{}" in {}"#, field, type_str)
+            }
+            DeserializeMaskErrorKind::EmptySegment => write!(f, "empty segment in {}", type_str),
+            DeserializeMaskErrorKind::InvalidWildcard => {
+                write!(f, r#"unexpected "*" in {}"#, type_str)
+            }
+        }
+    }
+}
+
+/// A path accepted by a `Maskable` type, along with the name of the type of the field it
+/// designates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathInfo {
+    pub path: String,
+    pub type_str: &'static str,
+}
+
+/// Types that can be masked, usually with `#[derive(Maskable)]`.
+///
+/// Every field of a derived type is reached by its own name, so giving two fields the same name
+/// with `rename` or `rename_all` is a compile error.
+///
+/// ```compile_fail
+/// use fieldmask::Maskable;
+///
+/// #[derive(Maskable)]
+/// #[fieldmask(rename_all = "camelCase")]
+/// struct Parent {
+///     field_one: String,
+///     #[fieldmask(rename = "fieldOne")]
+///     other: String,
+/// }
+/// ```
 pub trait Maskable: Sized {
-    type Mask;
+    /// The bounds are required here rather than where masks are used, so that the mask of a
+    /// recursive type doesn't depend on itself to implement them.
+    type Mask: Clone
+        + Default
+        + PartialEq
+        + fmt::Debug
+        + Not<Output = Self::Mask>
+        + BitAnd<Output = Self::Mask>
+        + BitOr<Output = Self::Mask>
+        + BitXor<Output = Self::Mask>;
+
+    /// The name of the type, as written in `PathInfo`.
+    const TYPE_STR: &'static str;
+
+    /// Whether the type is a leaf, whose mask is a `bool`. `#[fieldmask(packed)]` stores the masks
+    /// of the leaf fields of a type in bits.
+    const LEAF: bool = false;
 
     /// Perform a 'bitor' operation between the `mask` and a fieldmask in string format.
     /// When the function returns Ok, `mask` should be modified to include fields in
-    /// `field_mask_segs`.
+    /// `field_mask_segs`. When it returns Err, `mask` should be left untouched.
     fn try_bitor_assign_mask(
         // Take a reference here instead of the ownership. Because:
         // 1. We may want to try performing other operations on `mask` if the current one doesn't
@@ -24,51 +241,250 @@ pub trait Maskable: Sized {
         // Take a slice of segments instead of a full fieldmask string. Because:
         // 1. It's easier to perform pattern matching on slices.
         // 2. It's easier to distinguish empty fieldmask (e.g. "") and empty tail (e.g. "parent.").
-        field_mask_segs: &[&str],
+        field_mask_segs: &[Segment<&str>],
     ) -> Result<(), DeserializeMaskError>;
+
+    /// Append the paths included in `mask` to `paths`, each one prefixed with `prefix`.
+    /// A field whose mask is full is written as a single path instead of one path per leaf.
+    ///
+    /// By default, the paths listed by `append_all_paths` that `mask` contains are written, which
+    /// leaves out the map keys and vector indices that are masked on their own.
+    fn append_mask_paths(
+        mask: &Self::Mask,
+        prefix: &str,
+        paths: &mut Vec<String>,
+    ) -> Result<(), ToPathsError> {
+        if !prefix.is_empty() && *mask == !Self::Mask::default() {
+            paths.push(prefix.into());
+            return Ok(());
+        }
+        let mut written = Vec::<String>::new();
+        for PathInfo { path, .. } in Self::all_paths() {
+            let below_written = written.iter().any(|parent| {
+                path.strip_prefix(parent.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+            });
+            if below_written {
+                continue;
+            }
+            let contained = parse_path(&path).is_ok_and(|segs| {
+                let segs = segs.iter().map(Segment::as_deref).collect::<Vec<_>>();
+                Self::mask_contains(mask, &segs).unwrap_or(false)
+            });
+            if contained {
+                paths.push(join_path(prefix, &path));
+                written.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether everything under the field designated by `field_mask_segs` is included in
+    /// `mask`.
+    ///
+    /// By default, the mask of the field is built with `try_bitor_assign_mask` and compared with
+    /// `mask`.
+    fn mask_contains(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        let mut field_mask = Self::Mask::default();
+        Self::try_bitor_assign_mask(&mut field_mask, field_mask_segs)?;
+        Ok(mask.clone() & field_mask.clone() == field_mask)
+    }
+
+    /// Check whether anything under the field designated by `field_mask_segs` is included in
+    /// `mask`.
+    ///
+    /// By default, the mask of the field is built with `try_bitor_assign_mask` and compared with
+    /// `mask`.
+    fn mask_intersects(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        let mut field_mask = Self::Mask::default();
+        Self::try_bitor_assign_mask(&mut field_mask, field_mask_segs)?;
+        Ok(mask.clone() & field_mask != Self::Mask::default())
+    }
+
+    /// Append every path under `prefix` that the type accepts to `paths`. `prefix` itself is not
+    /// appended. The elements of vectors and the values of maps are written as `*`, and the paths
+    /// under a pointer are not listed.
+    ///
+    /// By default, no path is appended, as for a leaf.
+    fn append_all_paths(_prefix: &str, _paths: &mut Vec<PathInfo>) {}
+
+    /// Append the names of the fields of the type to `names`, including the ones of the fields of
+    /// its flattened fields.
+    fn append_field_names(_names: &mut Vec<&'static str>) {}
+
+    /// Append `field_mask_segs` to `renamed` with every field name replaced by its name in the
+    /// proto3 JSON format if `to_json` is set, or the other way around. The JSON name of a field is
+    /// the lowerCamelCase of its name. Map keys and vector indices are kept as is.
+    ///
+    /// Returns false if the first segment names no field of the type, in which case the segments
+    /// are appended as they are.
+    fn append_json_segs<'a>(
+        field_mask_segs: &[Segment<&'a str>],
+        _to_json: bool,
+        renamed: &mut Vec<Segment<&'a str>>,
+    ) -> bool {
+        renamed.extend_from_slice(field_mask_segs);
+        true
+    }
+
+    /// List every path that the type accepts, both leaves and intermediate fields.
+    fn all_paths() -> Vec<PathInfo> {
+        let mut paths = Vec::new();
+        Self::append_all_paths("", &mut paths);
+        paths
+    }
+}
+
+/// Join a path and a segment.
+pub(crate) fn join_path(prefix: &str, seg: &str) -> String {
+    if prefix.is_empty() {
+        seg.into()
+    } else {
+        format!("{}.{}", prefix, seg)
+    }
 }
 
 pub trait SelfMaskable: Maskable {
     /// Implementation of the application process of a mask.
     fn apply_mask(&mut self, src: Self, mask: Self::Mask);
+
+    /// Implementation of the projection process of a mask. Fields that are not included in
+    /// `mask` should be reset to their default values.
+    fn project_mask(&mut self, mask: &Self::Mask);
 }
 
 pub trait OptionMaskable: Maskable {
     /// Implementation of the application process of a mask.
     fn apply_mask(&mut self, src: Self, mask: Self::Mask) -> bool;
+
+    /// Implementation of the projection process of a mask.
+    /// Returns false if nothing is left after the projection.
+    fn project_mask(&mut self, mask: &Self::Mask) -> bool;
+}
+
+/// The application of a mask from a borrowed source, which is only implemented when the leaves
+/// can be cloned.
+pub trait SelfMaskableRef: SelfMaskable {
+    /// Same as `apply_mask`, but only clones the masked leaves of `src`.
+    fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask);
+}
+
+pub trait OptionMaskableRef: OptionMaskable {
+    /// Same as `apply_mask`, but only clones the masked leaves of `src`.
+    fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) -> bool;
+}
+
+impl<T: SelfMaskableRef + Default> OptionMaskableRef for T {
+    fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) -> bool {
+        SelfMaskableRef::apply_mask_ref(self, src, mask);
+        true
+    }
+}
+
+/// The comparison of two objects, which is only implemented when their leaves can be compared,
+/// so that masking a type doesn't require it.
+pub trait DiffMaskable: Maskable {
+    /// Implementation of the comparison process of two objects. The returned mask should
+    /// include the leaves that are different, so that applying it to `self` with `other` as the
+    /// source gives back `other`.
+    fn diff_mask(&self, other: &Self) -> Self::Mask;
 }
 
-impl<T: SelfMaskable> OptionMaskable for T
-where
-    T: Default,
-    T::Mask: PartialEq,
-{
+/// The application of a mask that reports what it changed, which is only implemented when the
+/// leaves can be compared.
+pub trait SelfMaskableChanges: SelfMaskable + DiffMaskable {
+    /// Same as `apply_mask`, but returns the mask of the leaves that changed, i.e. the result of
+    /// `diff_mask` between `self` before and after the application.
+    fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> Self::Mask;
+}
+
+pub trait OptionMaskableChanges: OptionMaskable + DiffMaskable {
+    /// Same as `apply_mask`, but also returns the mask of the leaves that changed. The mask is
+    /// meaningless if the object is removed.
+    fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> (bool, Self::Mask);
+}
+
+impl<T: SelfMaskableChanges + Default> OptionMaskableChanges for T {
+    fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> (bool, Self::Mask) {
+        (
+            true,
+            SelfMaskableChanges::apply_mask_with_changes(self, src, mask),
+        )
+    }
+}
+
+impl<T: SelfMaskable + Default> OptionMaskable for T {
     fn apply_mask(&mut self, src: Self, mask: Self::Mask) -> bool {
         self.apply_mask(src, mask);
         true
     }
+
+    fn project_mask(&mut self, mask: &Self::Mask) -> bool {
+        SelfMaskable::project_mask(self, mask);
+        true
+    }
 }
 
-impl<T: Maskable> Maskable for Option<T>
-where
-    T: Default,
-    T::Mask: PartialEq,
-{
+impl<T: Maskable + Default> Maskable for Option<T> {
     type Mask = T::Mask;
 
+    const TYPE_STR: &'static str = T::TYPE_STR;
+
+    const LEAF: bool = T::LEAF;
+
     fn try_bitor_assign_mask(
         mask: &mut Self::Mask,
-        field_mask_segs: &[&str],
+        field_mask_segs: &[Segment<&str>],
     ) -> Result<(), DeserializeMaskError> {
         T::try_bitor_assign_mask(mask, field_mask_segs)
     }
+
+    fn append_mask_paths(
+        mask: &Self::Mask,
+        prefix: &str,
+        paths: &mut Vec<String>,
+    ) -> Result<(), ToPathsError> {
+        T::append_mask_paths(mask, prefix, paths)
+    }
+
+    fn mask_contains(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        T::mask_contains(mask, field_mask_segs)
+    }
+
+    fn mask_intersects(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        T::mask_intersects(mask, field_mask_segs)
+    }
+
+    fn append_all_paths(prefix: &str, paths: &mut Vec<PathInfo>) {
+        T::append_all_paths(prefix, paths)
+    }
+
+    fn append_json_segs<'a>(
+        field_mask_segs: &[Segment<&'a str>],
+        to_json: bool,
+        renamed: &mut Vec<Segment<&'a str>>,
+    ) -> bool {
+        T::append_json_segs(field_mask_segs, to_json, renamed)
+    }
+
+    fn append_field_names(names: &mut Vec<&'static str>) {
+        T::append_field_names(names)
+    }
 }
 
-impl<T: OptionMaskable> SelfMaskable for Option<T>
-where
-    T: Default,
-    T::Mask: PartialEq + Default,
-{
+impl<T: OptionMaskable + Default> SelfMaskable for Option<T> {
     fn apply_mask(&mut self, src: Self, mask: Self::Mask) {
         if mask == Self::Mask::default() {
             return;
@@ -82,8 +498,8 @@ where
                 }
                 None => *self = None,
             },
-            None => match src {
-                Some(o) => {
+            None => {
+                if let Some(o) = src {
                     let mut new = T::default();
                     if new.apply_mask(o, mask) {
                         *self = Some(new);
@@ -91,89 +507,195 @@ where
                         *self = None;
                     }
                 }
-                None => {}
+            }
+        }
+    }
+
+    fn project_mask(&mut self, mask: &Self::Mask) {
+        if let Some(s) = self {
+            if *mask == Self::Mask::default() || !s.project_mask(mask) {
+                *self = None;
+            }
+        }
+    }
+}
+
+impl<T: OptionMaskableRef + Default> SelfMaskableRef for Option<T> {
+    fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) {
+        if *mask == Self::Mask::default() {
+            return;
+        }
+        match self {
+            Some(s) => match src {
+                Some(o) => {
+                    if !s.apply_mask_ref(o, mask) {
+                        *self = None;
+                    }
+                }
+                None => *self = None,
             },
+            None => {
+                if let Some(o) = src {
+                    let mut new = T::default();
+                    if new.apply_mask_ref(o, mask) {
+                        *self = Some(new);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: OptionMaskableChanges + Default> SelfMaskableChanges for Option<T> {
+    /// Adding or removing the value changes the fields in which it differs from the default one.
+    fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> Self::Mask {
+        if mask == Self::Mask::default() {
+            return mask;
+        }
+        match (self.as_mut(), src) {
+            // A value that isn't kept is left untouched, so that it can still be compared.
+            (Some(s), Some(o)) => {
+                let (keep, changes) = s.apply_mask_with_changes(o, mask);
+                if keep {
+                    return changes;
+                }
+            }
+            (Some(_), None) => {}
+            (None, Some(o)) => {
+                let mut new = T::default();
+                if !new.apply_mask(o, mask) {
+                    return Self::Mask::default();
+                }
+                *self = Some(new);
+                return None.diff_mask(self);
+            }
+            (None, None) => return Self::Mask::default(),
+        }
+        self.take().diff_mask(&None)
+    }
+}
+
+impl<T: DiffMaskable + Default> DiffMaskable for Option<T> {
+    /// A missing value is compared as the default one, so that only the fields that the present
+    /// value sets are included, or the whole value if it sets none.
+    fn diff_mask(&self, other: &Self) -> Self::Mask {
+        let mask = match (self, other) {
+            (Some(s), Some(o)) => return s.diff_mask(o),
+            (None, None) => return Self::Mask::default(),
+            (Some(value), None) | (None, Some(value)) => T::default().diff_mask(value),
+        };
+        if mask == Self::Mask::default() {
+            !mask
+        } else {
+            mask
         }
     }
 }
 
+/// Implement the traits and the typed paths for a leaf, which is always replaced as a whole. Any
+/// value can be masked and applied, while the other operations require cloning or comparing it.
 macro_rules! maskable {
-    ($T:ident) => {
-        impl Maskable for $T {
+    (impl<$($P:ident),*> for $T:ty as $name:expr) => {
+        impl<$($P),*> Maskable for $T {
             type Mask = bool;
 
+            const TYPE_STR: &'static str = $name;
+
+            const LEAF: bool = true;
+
             fn try_bitor_assign_mask(
                 mask: &mut Self::Mask,
-                field_mask_segs: &[&str],
+                field_mask_segs: &[Segment<&str>],
             ) -> Result<(), DeserializeMaskError> {
-                if field_mask_segs.len() == 0 {
+                if let [] | [Segment::Unquoted("*")] = field_mask_segs {
                     *mask = true;
                     Ok(())
                 } else {
-                    Err(DeserializeMaskError {
-                        type_str: stringify!($T),
-                        field: field_mask_segs[0].into(),
-                        depth: 0,
-                    })
+                    Err(DeserializeMaskError::below_leaf(
+                        $name,
+                        field_mask_segs[0],
+                    ))
+                }
+            }
+
+            fn append_mask_paths(
+                mask: &Self::Mask,
+                prefix: &str,
+                paths: &mut Vec<String>,
+            ) -> Result<(), ToPathsError> {
+                if *mask {
+                    paths.push(prefix.into());
+                }
+                Ok(())
+            }
+
+            fn mask_contains(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                Self::mask_intersects(mask, field_mask_segs)
+            }
+
+            fn mask_intersects(
+                mask: &Self::Mask,
+                field_mask_segs: &[Segment<&str>],
+            ) -> Result<bool, DeserializeMaskError> {
+                if let [] | [Segment::Unquoted("*")] = field_mask_segs {
+                    Ok(*mask)
+                } else {
+                    Err(DeserializeMaskError::below_leaf(
+                        $name,
+                        field_mask_segs[0],
+                    ))
                 }
             }
         }
 
-        impl SelfMaskable for $T {
+        impl<$($P),*> SelfMaskable for $T {
             fn apply_mask(&mut self, other: Self, mask: Self::Mask) {
                 if mask {
                     *self = other;
                 }
             }
-        }
-    };
-}
-
-maskable!(bool);
-maskable!(char);
-
-maskable!(f32);
-maskable!(f64);
 
-maskable!(i8);
-maskable!(u8);
-maskable!(i16);
-maskable!(u16);
-maskable!(i32);
-maskable!(u32);
-maskable!(i64);
-maskable!(u64);
-maskable!(i128);
-maskable!(u128);
-maskable!(isize);
-maskable!(usize);
-
-maskable!(String);
+            fn project_mask(&mut self, mask: &Self::Mask) {
+                if !*mask {
+                    *self = Self::default();
+                }
+            }
+        }
 
-impl<T> Maskable for Vec<T> {
-    type Mask = bool;
+        impl<$($P),*> SelfMaskableRef for $T where Self: Clone {
+            fn apply_mask_ref(&mut self, other: &Self, mask: &Self::Mask) {
+                if *mask {
+                    self.clone_from(other);
+                }
+            }
+        }
 
-    fn try_bitor_assign_mask(
-        mask: &mut Self::Mask,
-        field_mask_segs: &[&str],
-    ) -> Result<(), DeserializeMaskError> {
-        if field_mask_segs.len() == 0 {
-            *mask = true;
-            Ok(())
-        } else {
-            Err(DeserializeMaskError {
-                type_str: "Vec",
-                field: field_mask_segs[0].into(),
-                depth: 0,
-            })
+        impl<$($P),*> SelfMaskableChanges for $T where Self: PartialEq {
+            fn apply_mask_with_changes(&mut self, other: Self, mask: Self::Mask) -> Self::Mask {
+                let changed = mask && *self != other;
+                if mask {
+                    *self = other;
+                }
+                changed
+            }
         }
-    }
-}
 
-impl<T> SelfMaskable for Vec<T> {
-    fn apply_mask(&mut self, other: Self, mask: Self::Mask) {
-        if mask {
-            *self = other;
+        impl<$($P),*> DiffMaskable for $T where Self: PartialEq {
+            fn diff_mask(&self, other: &Self) -> Self::Mask {
+                self != other
+            }
         }
-    }
+
+        leaf_paths!(impl<$($P),*> for $T);
+    };
+    ($($T:ident),*) => {
+        $(maskable!(impl<> for $T as stringify!($T));)*
+    };
 }
+
+maskable!(bool, char, f32, f64, String);
+maskable!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
+maskable!(impl<T> for Vec<T> as "Vec");
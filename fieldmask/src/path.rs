@@ -0,0 +1,231 @@
+use core::marker::PhantomData;
+
+use crate::{field_mask::FieldMask, maskable::Maskable};
+
+/// Lift a mask of `T` into a mask of `R`, the type a typed path starts from.
+pub trait Lift<T: Maskable, R: Maskable>: Copy {
+    fn lift(self, mask: FieldMask<T>) -> FieldMask<R>;
+}
+
+/// The start of a typed path.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RootLift;
+
+impl<R: Maskable> Lift<R, R> for RootLift {
+    fn lift(self, mask: FieldMask<R>) -> FieldMask<R> {
+        mask
+    }
+}
+
+/// A field of type `T` in `P`, where `P` is lifted into the root type by `parent`.
+pub struct FieldLift<L, P: Maskable, T: Maskable> {
+    parent: L,
+    field: fn(FieldMask<T>) -> FieldMask<P>,
+}
+
+impl<L, P: Maskable, T: Maskable> FieldLift<L, P, T> {
+    pub fn new(parent: L, field: fn(FieldMask<T>) -> FieldMask<P>) -> Self {
+        FieldLift { parent, field }
+    }
+}
+
+// Derived `Clone` and `Copy` would require `P` and `T` to implement them.
+impl<L: Copy, P: Maskable, T: Maskable> Clone for FieldLift<L, P, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<L: Copy, P: Maskable, T: Maskable> Copy for FieldLift<L, P, T> {}
+
+impl<L, P, T, R> Lift<T, R> for FieldLift<L, P, T>
+where
+    L: Lift<P, R>,
+    P: Maskable,
+    T: Maskable,
+    R: Maskable,
+{
+    fn lift(self, mask: FieldMask<T>) -> FieldMask<R> {
+        self.parent.lift((self.field)(mask))
+    }
+}
+
+/// The value of an `Option<T>` that is lifted into the root type by `L`. `Option<T>` shares its
+/// mask with `T`, so the paths of `T` are used for both.
+#[derive(Clone, Copy, Debug)]
+pub struct OptionLift<L>(L);
+
+impl<L, T, R> Lift<T, R> for OptionLift<L>
+where
+    L: Lift<Option<T>, R>,
+    T: Maskable,
+    Option<T>: Maskable<Mask = T::Mask>,
+    R: Maskable,
+{
+    fn lift(self, mask: FieldMask<T>) -> FieldMask<R> {
+        self.0.lift(FieldMask::from_mask(mask.into_mask()))
+    }
+}
+
+/// Types whose fields can be reached with typed paths, e.g. `Parent::paths().child_1()`.
+///
+/// `#[derive(Maskable)]` generates a path type with one method per field, and vectors have a
+/// `VecPath` to their elements. The other types only have a `LeafPath`.
+pub trait MaskablePaths<R: Maskable, L: Lift<Self, R>>: Maskable + Sized {
+    type Paths: Copy;
+
+    fn paths(lift: L) -> Self::Paths;
+}
+
+/// Typed paths that lead to a flattened field of type `T`.
+///
+/// The fields of a flattened field are reached without naming it, e.g.
+/// `Parent::paths().variant_one()`, just like in string paths. `#[derive(Maskable)]` generates a
+/// `FlattenedPaths` trait for every type, with one method per field, which is implemented by the
+/// typed paths that flatten a field of the type or of an `Option` of it.
+///
+/// The flattened field itself has no path, since string paths can't name it either.
+///
+/// ```compile_fail
+/// use fieldmask::{field_mask, Maskable};
+///
+/// #[derive(Maskable)]
+/// struct Parent {
+///     #[fieldmask(flatten)]
+///     one_of: Option<OneOf>,
+/// }
+///
+/// #[derive(Maskable)]
+/// enum OneOf {
+///     VariantOne(String),
+/// }
+///
+/// impl Default for OneOf {
+///     fn default() -> Self {
+///         Self::VariantOne(String::new())
+///     }
+/// }
+///
+/// let mask = field_mask!(Parent; one_of.variant_one);
+/// ```
+pub trait Flattened<T> {
+    type Paths;
+
+    fn flattened(self) -> Self::Paths;
+}
+
+/// A typed path from `R` to a field of type `T` that can't be walked any further.
+pub struct LeafPath<R, T, L> {
+    lift: L,
+    _marker: PhantomData<fn(T) -> R>,
+}
+
+impl<R, T, L> LeafPath<R, T, L> {
+    pub(crate) fn new(lift: L) -> Self {
+        LeafPath {
+            lift,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R, T, L: Copy> Clone for LeafPath<R, T, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R, T, L: Copy> Copy for LeafPath<R, T, L> {}
+
+impl<R, T, L> From<LeafPath<R, T, L>> for FieldMask<R>
+where
+    R: Maskable,
+    T: Maskable,
+    L: Lift<T, R>,
+{
+    fn from(path: LeafPath<R, T, L>) -> Self {
+        path.lift.lift(!FieldMask::default())
+    }
+}
+
+impl<T, R, L> MaskablePaths<R, L> for Option<T>
+where
+    T: MaskablePaths<R, OptionLift<L>>,
+    Option<T>: Maskable<Mask = T::Mask>,
+    R: Maskable,
+    L: Lift<Option<T>, R>,
+{
+    type Paths = T::Paths;
+
+    fn paths(lift: L) -> Self::Paths {
+        T::paths(OptionLift(lift))
+    }
+}
+
+/// Implement `MaskablePaths` for a type that has no typed paths below it. The leaves that
+/// `maskable!` implements get theirs from it.
+macro_rules! leaf_paths {
+    (impl<$($P:ident),*> for $T:ty) => {
+        impl<$($P,)* R, L> $crate::MaskablePaths<R, L> for $T
+        where
+            $T: $crate::Maskable,
+            R: $crate::Maskable,
+            L: $crate::Lift<$T, R>,
+        {
+            type Paths = $crate::LeafPath<R, $T, L>;
+
+            fn paths(lift: L) -> Self::Paths {
+                $crate::LeafPath::new(lift)
+            }
+        }
+    };
+}
+
+pub(crate) use leaf_paths;
+
+leaf_paths!(impl<K, V, S> for std::collections::HashMap<K, V, S>);
+leaf_paths!(impl<K, V> for std::collections::BTreeMap<K, V>);
+
+/// Build a `FieldMask` from paths that are checked at compile time.
+///
+/// ```
+/// use fieldmask::{field_mask, Maskable};
+///
+/// #[derive(Maskable)]
+/// struct Parent {
+///     primitive: String,
+///     child: Child,
+/// }
+///
+/// #[derive(Maskable)]
+/// struct Child {
+///     field_one: String,
+///     field_two: u32,
+/// }
+///
+/// let mask = field_mask!(Parent; primitive, child.field_two);
+/// assert_eq!(mask.to_paths().unwrap(), vec!["primitive", "child.field_two"]);
+/// ```
+///
+/// Every path is resolved with the typed paths generated by `#[derive(Maskable)]`, so a typo is a
+/// compile error and nothing is parsed at runtime.
+///
+/// ```compile_fail
+/// use fieldmask::{field_mask, Maskable};
+///
+/// #[derive(Maskable)]
+/// struct Child {
+///     field_one: String,
+/// }
+///
+/// let mask = field_mask!(Child; feld_one);
+/// ```
+#[macro_export]
+macro_rules! field_mask {
+    ($T:ty; $($($seg:ident).+),* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut mask = $crate::FieldMask::<$T>::default();
+        $(mask |= $crate::FieldMask::from(<$T>::paths()$(.$seg())+);)*
+        mask
+    }};
+}
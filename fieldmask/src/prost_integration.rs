@@ -0,0 +1,27 @@
+use core::convert::TryFrom;
+
+use crate::{
+    field_mask::{DeserializeFieldMaskError, FieldMask, FieldMaskInput, ToPathsError},
+    maskable::Maskable,
+};
+
+impl<T> TryFrom<prost_types::FieldMask> for FieldMask<T>
+where
+    T: Maskable,
+{
+    type Error = DeserializeFieldMaskError;
+
+    fn try_from(value: prost_types::FieldMask) -> Result<Self, Self::Error> {
+        Self::try_from(FieldMaskInput(value.paths.iter().map(String::as_str)))
+    }
+}
+
+impl<T: Maskable> TryFrom<FieldMask<T>> for prost_types::FieldMask {
+    type Error = ToPathsError;
+
+    fn try_from(value: FieldMask<T>) -> Result<Self, Self::Error> {
+        Ok(prost_types::FieldMask {
+            paths: value.to_paths()?,
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use core::{convert::TryFrom, fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::borrow::Cow;
+
+use crate::{
+    field_mask::{parse_path, split_unquoted, FieldMask, FieldMaskInput, Segment},
+    maskable::Maskable,
+};
+
+/// Rename the fields of `path` between their names in paths and their names in the proto3 JSON
+/// format, with the names known to `T`. A path that can't be parsed is given back as is, so that
+/// parsing the mask reports it.
+fn rename_path<T: Maskable>(path: &str, to_json: bool) -> String {
+    let segs = match parse_path(path) {
+        Ok(segs) => segs,
+        Err(_) => return path.into(),
+    };
+    let segs = segs.iter().map(Segment::as_deref).collect::<Vec<_>>();
+    let mut renamed = Vec::new();
+    T::append_json_segs(&segs, to_json, &mut renamed);
+    renamed
+        .iter()
+        .map(|seg| match seg {
+            Segment::Unquoted(name) => Cow::Borrowed(*name),
+            Segment::Quoted(name) => Cow::Owned(format!("`{}`", name.replace('`', "``"))),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Serialize the mask into the proto3 JSON format, i.e. a single string of comma separated
+/// lowerCamelCase paths. Map keys are written as they are.
+impl<T: Maskable> Serialize for FieldMask<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut paths = Vec::new();
+        for path in self.to_paths().map_err(ser::Error::custom)? {
+            let json = rename_path::<T>(&path, true);
+            // Two fields whose names have the same lowerCamelCase can't be told apart.
+            if rename_path::<T>(&json, false) != path {
+                return Err(ser::Error::custom(format!(
+                    "{} can't be written in the proto3 JSON format",
+                    path,
+                )));
+            }
+            paths.push(json);
+        }
+        serializer.serialize_str(&paths.join(","))
+    }
+}
+
+/// Deserialize the mask from the proto3 JSON format, i.e. a single string of comma separated
+/// lowerCamelCase paths.
+impl<'de, T> Deserialize<'de> for FieldMask<T>
+where
+    T: Maskable,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FieldMaskVisitor(PhantomData))
+    }
+}
+
+struct FieldMaskVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for FieldMaskVisitor<T>
+where
+    T: Maskable,
+{
+    type Value = FieldMask<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string of comma separated field paths")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        let paths = split_unquoted(value, ',')
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .map(|path| rename_path::<T>(path, false))
+            .collect::<Vec<_>>();
+        FieldMask::try_from(FieldMaskInput(paths.iter().map(String::as_str)))
+            .map_err(de::Error::custom)
+    }
+}
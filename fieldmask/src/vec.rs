@@ -0,0 +1,558 @@
+use core::{
+    marker::PhantomData,
+    mem,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+};
+use std::collections::BTreeMap;
+
+use crate::{
+    field_mask::{FieldMask, Segment, ToPathsError},
+    maskable::{
+        join_path, DeserializeMaskError, DiffMaskable, Maskable, PathInfo, SelfMaskable,
+        SelfMaskableChanges, SelfMaskableRef,
+    },
+    path::{Lift, MaskablePaths},
+};
+
+/// Mask of a repeated field.
+///
+/// If `all` is set, the whole vector is replaced. Otherwise the elements are updated one by one:
+/// every index can have its own mask, and the indices without one share `each`. `all` is only set
+/// when every element is fully masked, so that a mask that leaves out some fields of an element
+/// never replaces it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VecMask<M> {
+    all: bool,
+    each: M,
+    indices: BTreeMap<usize, M>,
+}
+
+impl<M: Default> Default for VecMask<M> {
+    fn default() -> Self {
+        VecMask {
+            all: false,
+            each: M::default(),
+            indices: BTreeMap::new(),
+        }
+    }
+}
+
+impl<M: Default> VecMask<M> {
+    /// A mask that includes `mask` of the element at `index`, or of every element if `index` is
+    /// `None`.
+    fn element(index: Option<usize>, mask: M) -> Self {
+        match index {
+            Some(index) => {
+                let mut indices = BTreeMap::new();
+                indices.insert(index, mask);
+                VecMask {
+                    all: false,
+                    each: M::default(),
+                    indices,
+                }
+            }
+            None => VecMask {
+                all: false,
+                each: mask,
+                indices: BTreeMap::new(),
+            },
+        }
+    }
+}
+
+impl<M: Clone> VecMask<M> {
+    /// The mask of the element at `index`.
+    pub fn get(&self, index: usize) -> M {
+        self.indices.get(&index).unwrap_or(&self.each).clone()
+    }
+}
+
+impl<M: Clone + PartialEq> VecMask<M> {
+    /// Combine two vector masks index by index, using `each` of either side for the indices it
+    /// has no mask for. An index whose mask ends up equal to the combined `each` is covered by it,
+    /// so its entry is dropped.
+    fn merge(self, rhs: Self, all: bool, f: impl Fn(M, M) -> M) -> Self {
+        let VecMask {
+            each: lhs_each,
+            indices: lhs_indices,
+            ..
+        } = self;
+        let VecMask {
+            each: rhs_each,
+            indices: mut rhs_indices,
+            ..
+        } = rhs;
+        let mut indices = BTreeMap::new();
+        for (index, lhs) in lhs_indices {
+            let rhs = rhs_indices
+                .remove(&index)
+                .unwrap_or_else(|| rhs_each.clone());
+            indices.insert(index, f(lhs, rhs));
+        }
+        for (index, rhs) in rhs_indices {
+            indices.insert(index, f(lhs_each.clone(), rhs));
+        }
+        let each = f(lhs_each, rhs_each);
+        indices.retain(|_, mask| *mask != each);
+        VecMask { all, each, indices }
+    }
+}
+
+impl<M> BitAnd for VecMask<M>
+where
+    M: BitAnd<Output = M> + Clone + PartialEq,
+{
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let all = self.all & rhs.all;
+        self.merge(rhs, all, |l, r| l & r)
+    }
+}
+
+impl<M> BitAndAssign for VecMask<M>
+where
+    M: BitAnd<Output = M> + Clone + Default + PartialEq,
+{
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) & rhs;
+    }
+}
+
+impl<M> BitOr for VecMask<M>
+where
+    M: BitOr<Output = M> + Clone + PartialEq,
+{
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let all = self.all | rhs.all;
+        self.merge(rhs, all, |l, r| l | r)
+    }
+}
+
+impl<M> BitOrAssign for VecMask<M>
+where
+    M: BitOr<Output = M> + Clone + Default + PartialEq,
+{
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) | rhs;
+    }
+}
+
+impl<M> BitXor for VecMask<M>
+where
+    M: BitXor<Output = M> + Not<Output = M> + Clone + Default + PartialEq,
+{
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let all = self.all ^ rhs.all;
+        let mut mask = self.merge(rhs, all, |l, r| l ^ r);
+        mask.all &= mask.each == !M::default() && mask.indices.is_empty();
+        mask
+    }
+}
+
+impl<M> BitXorAssign for VecMask<M>
+where
+    M: BitXor<Output = M> + Not<Output = M> + Clone + Default + PartialEq,
+{
+    fn bitxor_assign(&mut self, rhs: Self) {
+        *self = mem::take(self) ^ rhs;
+    }
+}
+
+impl<M: Not<Output = M> + Default + PartialEq> Not for VecMask<M> {
+    type Output = Self;
+
+    /// The whole vector is only included if no field of any element was.
+    fn not(self) -> Self::Output {
+        VecMask {
+            all: !self.all && self.each == M::default() && self.indices.is_empty(),
+            each: !self.each,
+            indices: self
+                .indices
+                .into_iter()
+                .map(|(index, mask)| (index, !mask))
+                .collect(),
+        }
+    }
+}
+
+fn parse_index(index: &Segment<&str>) -> Result<usize, DeserializeMaskError> {
+    index
+        .name()
+        .parse()
+        .map_err(|_| DeserializeMaskError::invalid_index("Vec", *index))
+}
+
+/// The mask of a vector whose elements are masked one by one.
+///
+/// A `Vec<T>` is a leaf that is replaced as a whole, whatever `T` is. A `Vec<T>` field marked
+/// with `#[fieldmask(elements)]` has a `FieldMask<Elements<Vec<T>>>` instead, which also accepts
+/// paths to the fields of its elements, like `items.*.name` and `items.3.name`.
+pub struct Elements<V>(PhantomData<fn() -> V>);
+
+impl<T> Maskable for Elements<Vec<T>>
+where
+    T: Maskable,
+{
+    type Mask = VecMask<T::Mask>;
+
+    const TYPE_STR: &'static str = "Vec";
+
+    /// `items` and `items.*` select the whole vector, `items.*.name` selects `name` of every
+    /// element and `items.3.name` selects `name` of the element at index 3.
+    fn try_bitor_assign_mask(
+        mask: &mut Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<(), DeserializeMaskError> {
+        match field_mask_segs {
+            [] | [Segment::Unquoted("*")] => *mask = !VecMask::default(),
+            [index, tail @ ..] => {
+                let mut element = T::Mask::default();
+                T::try_bitor_assign_mask(&mut element, tail).map_err(|mut e| {
+                    e.depth += 1;
+                    e
+                })?;
+                let index = match index {
+                    Segment::Unquoted("*") => None,
+                    _ => Some(parse_index(index)?),
+                };
+                *mask |= VecMask::element(index, element);
+            }
+        }
+        Ok(())
+    }
+
+    /// Every element being fully masked is written as `items.*.*`, since `items.*` is the whole
+    /// vector. An index that leaves out some of the fields included by `items.*` can't be written
+    /// as paths.
+    fn append_mask_paths(
+        mask: &Self::Mask,
+        prefix: &str,
+        paths: &mut Vec<String>,
+    ) -> Result<(), ToPathsError> {
+        if mask.all {
+            paths.push(prefix.into());
+            return Ok(());
+        }
+        let elements = Some(("*".to_string(), &mask.each))
+            .into_iter()
+            .chain(mask.indices.iter().map(|(i, m)| (i.to_string(), m)));
+        for (seg, element) in elements {
+            if seg != "*" && element.clone() & mask.each.clone() != mask.each {
+                return Err(ToPathsError {
+                    path: prefix.into(),
+                });
+            }
+            if *element == T::Mask::default() {
+                continue;
+            }
+            let path = join_path(prefix, &seg);
+            if *element != !T::Mask::default() {
+                T::append_mask_paths(element, &path, paths)?;
+            } else if seg == "*" {
+                paths.push(join_path(&path, "*"));
+            } else {
+                paths.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    /// `items.*.name` is contained if `name` of every element is included, and intersects if
+    /// `name` of any element is included.
+    fn mask_contains(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        let result = match field_mask_segs {
+            [] | [Segment::Unquoted("*")] => return Ok(mask.all),
+            [Segment::Unquoted("*"), tail @ ..] => Some(&mask.each)
+                .into_iter()
+                .chain(mask.indices.values())
+                .map(|element| T::mask_contains(element, tail))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|results| results.into_iter().all(|contains| contains)),
+            [index, tail @ ..] => {
+                let index = parse_index(index)?;
+                T::mask_contains(mask.indices.get(&index).unwrap_or(&mask.each), tail)
+            }
+        };
+        result.map_err(|mut e| {
+            e.depth += 1;
+            e
+        })
+    }
+
+    fn mask_intersects(
+        mask: &Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<bool, DeserializeMaskError> {
+        let result = match field_mask_segs {
+            [] | [Segment::Unquoted("*")] => return Ok(*mask != VecMask::default()),
+            [Segment::Unquoted("*"), tail @ ..] => Some(&mask.each)
+                .into_iter()
+                .chain(mask.indices.values())
+                .map(|element| T::mask_intersects(element, tail))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|results| results.into_iter().any(|intersects| intersects)),
+            [index, tail @ ..] => {
+                let index = parse_index(index)?;
+                T::mask_intersects(mask.indices.get(&index).unwrap_or(&mask.each), tail)
+            }
+        };
+        result.map_err(|mut e| {
+            e.depth += 1;
+            e
+        })
+    }
+
+    fn append_all_paths(prefix: &str, paths: &mut Vec<PathInfo>) {
+        let path = join_path(prefix, "*");
+        paths.push(PathInfo {
+            path: path.clone(),
+            type_str: T::TYPE_STR,
+        });
+        T::append_all_paths(&path, paths);
+    }
+
+    fn append_json_segs<'a>(
+        field_mask_segs: &[Segment<&'a str>],
+        to_json: bool,
+        renamed: &mut Vec<Segment<&'a str>>,
+    ) -> bool {
+        if let [index, tail @ ..] = field_mask_segs {
+            renamed.push(*index);
+            T::append_json_segs(tail, to_json, renamed);
+        }
+        true
+    }
+}
+
+/// The operations of a `FieldMask` of a `SelfMaskable` type, for the mask of the elements of a
+/// vector.
+impl<T> Elements<Vec<T>>
+where
+    T: SelfMaskable + Default,
+{
+    /// Unless the whole vector is masked, elements are paired by index, and the elements that
+    /// only exist in one of `target` and `src` are left untouched.
+    pub fn apply(mask: FieldMask<Self>, target: &mut Vec<T>, src: Vec<T>) {
+        let mask = mask.into_mask();
+        if mask.all {
+            *target = src;
+            return;
+        }
+        for (index, (t, s)) in target.iter_mut().zip(src).enumerate() {
+            let element = mask.get(index);
+            if element != T::Mask::default() {
+                t.apply_mask(s, element);
+            }
+        }
+    }
+
+    pub fn project_in_place(mask: &FieldMask<Self>, target: &mut Vec<T>) {
+        let mask = mask.as_ref();
+        if mask.all {
+            return;
+        }
+        if *mask == VecMask::default() {
+            target.clear();
+            return;
+        }
+        for (index, t) in target.iter_mut().enumerate() {
+            t.project_mask(mask.indices.get(&index).unwrap_or(&mask.each));
+        }
+    }
+}
+
+impl<T> Elements<Vec<T>>
+where
+    T: SelfMaskableRef + Default,
+{
+    /// If the whole vector is masked, the elements of `target` are updated in place, and the
+    /// elements missing from `target` are built from their default values.
+    pub fn apply_ref(mask: &FieldMask<Self>, target: &mut Vec<T>, src: &[T]) {
+        let mask = mask.as_ref();
+        if mask.all {
+            let full = !T::Mask::default();
+            target.truncate(src.len());
+            for (index, s) in src.iter().enumerate() {
+                match target.get_mut(index) {
+                    Some(t) => t.apply_mask_ref(s, &full),
+                    None => {
+                        let mut new = T::default();
+                        new.apply_mask_ref(s, &full);
+                        target.push(new);
+                    }
+                }
+            }
+            return;
+        }
+        for (index, (t, s)) in target.iter_mut().zip(src).enumerate() {
+            let element = mask.indices.get(&index).unwrap_or(&mask.each);
+            if *element != T::Mask::default() {
+                t.apply_mask_ref(s, element);
+            }
+        }
+    }
+}
+
+impl<T> Elements<Vec<T>>
+where
+    T: SelfMaskableChanges + Default,
+{
+    pub fn apply_with_changes(
+        mask: FieldMask<Self>,
+        target: &mut Vec<T>,
+        src: Vec<T>,
+    ) -> FieldMask<Self> {
+        let mask = mask.into_mask();
+        if mask.all {
+            let changes = Self::diff(target, &src);
+            *target = src;
+            return changes;
+        }
+        let indices = target
+            .iter_mut()
+            .zip(src)
+            .enumerate()
+            .filter_map(|(index, (t, s))| {
+                let element = mask.get(index);
+                if element == T::Mask::default() {
+                    return None;
+                }
+                let changes = t.apply_mask_with_changes(s, element);
+                if changes == T::Mask::default() {
+                    None
+                } else {
+                    Some((index, changes))
+                }
+            })
+            .collect();
+        FieldMask::from_mask(VecMask {
+            all: false,
+            each: T::Mask::default(),
+            indices,
+        })
+    }
+}
+
+impl<T: DiffMaskable> Elements<Vec<T>> {
+    /// Vectors of different lengths are replaced as a whole. Otherwise the elements are compared
+    /// one by one.
+    pub fn diff(old: &[T], new: &[T]) -> FieldMask<Self> {
+        if old.len() != new.len() {
+            return !FieldMask::default();
+        }
+        let indices = old
+            .iter()
+            .zip(new)
+            .map(|(o, n)| o.diff_mask(n))
+            .enumerate()
+            .filter(|(_, mask)| *mask != T::Mask::default())
+            .collect();
+        FieldMask::from_mask(VecMask {
+            all: false,
+            each: T::Mask::default(),
+            indices,
+        })
+    }
+}
+
+/// The elements of a vector that is lifted into the root type by `parent`: the one at `index`, or
+/// all of them if `index` is `None`.
+#[derive(Clone, Copy, Debug)]
+pub struct ElementLift<L> {
+    parent: L,
+    index: Option<usize>,
+}
+
+impl<L, T, R> Lift<T, R> for ElementLift<L>
+where
+    L: Lift<Elements<Vec<T>>, R>,
+    T: Maskable,
+    Elements<Vec<T>>: Maskable<Mask = VecMask<T::Mask>>,
+    R: Maskable,
+{
+    fn lift(self, mask: FieldMask<T>) -> FieldMask<R> {
+        self.parent.lift(FieldMask::from_mask(VecMask::element(
+            self.index,
+            mask.into_mask(),
+        )))
+    }
+}
+
+/// A typed path from `R` to a vector of `T` whose elements are masked one by one.
+pub struct VecPath<R, T, L> {
+    lift: L,
+    _marker: PhantomData<fn(T) -> R>,
+}
+
+impl<R, T, L: Copy> Clone for VecPath<R, T, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<R, T, L: Copy> Copy for VecPath<R, T, L> {}
+
+impl<R, T, L> VecPath<R, T, L>
+where
+    R: Maskable,
+    T: Maskable,
+    Elements<Vec<T>>: Maskable<Mask = VecMask<T::Mask>>,
+    L: Lift<Elements<Vec<T>>, R>,
+{
+    /// The path to every element, like `items.*`.
+    pub fn each(self) -> T::Paths
+    where
+        T: MaskablePaths<R, ElementLift<L>>,
+    {
+        T::paths(ElementLift {
+            parent: self.lift,
+            index: None,
+        })
+    }
+
+    /// The path to the element at `index`, like `items.3`.
+    pub fn at(self, index: usize) -> T::Paths
+    where
+        T: MaskablePaths<R, ElementLift<L>>,
+    {
+        T::paths(ElementLift {
+            parent: self.lift,
+            index: Some(index),
+        })
+    }
+}
+
+impl<R, T, L> From<VecPath<R, T, L>> for FieldMask<R>
+where
+    R: Maskable,
+    Elements<Vec<T>>: Maskable,
+    L: Lift<Elements<Vec<T>>, R>,
+{
+    fn from(path: VecPath<R, T, L>) -> Self {
+        path.lift.lift(!FieldMask::default())
+    }
+}
+
+impl<T, R, L> MaskablePaths<R, L> for Elements<Vec<T>>
+where
+    Elements<Vec<T>>: Maskable,
+    R: Maskable,
+    L: Lift<Elements<Vec<T>>, R>,
+{
+    type Paths = VecPath<R, T, L>;
+
+    fn paths(lift: L) -> Self::Paths {
+        VecPath {
+            lift,
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable, PathInfo};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Collections {
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    labels: HashMap<String, String>,
+    regions: HashMap<String, Child>,
+    tags: Vec<String>,
+}
+
+fn info(path: &str, type_str: &'static str) -> PathInfo {
+    PathInfo {
+        path: path.into(),
+        type_str,
+    }
+}
+
+#[test]
+fn all_paths() {
+    assert_eq!(
+        Parent::all_paths(),
+        vec![
+            info("primitive", "String"),
+            info("child_1", "Child"),
+            info("child_1.field_one", "String"),
+            info("child_1.field_two", "u32"),
+            info("child_2", "Child"),
+            info("child_2.field_one", "String"),
+            info("child_2.field_two", "u32"),
+            info("variant_one", "String"),
+            info("variant_two", "Child"),
+            info("variant_two.field_one", "String"),
+            info("variant_two.field_two", "u32"),
+        ],
+    );
+}
+
+#[test]
+fn all_paths_of_collections() {
+    assert_eq!(
+        Collections::all_paths(),
+        vec![
+            info("items", "Vec"),
+            info("items.*", "Child"),
+            info("items.*.field_one", "String"),
+            info("items.*.field_two", "u32"),
+            info("labels", "HashMap"),
+            info("labels.*", "String"),
+            info("regions", "HashMap"),
+            info("regions.*", "Child"),
+            info("tags", "Vec"),
+        ],
+    );
+}
+
+#[test]
+fn all_paths_of_primitive() {
+    assert_eq!(u32::all_paths(), vec![]);
+}
+
+#[test]
+fn parse_all_paths() {
+    for info in Parent::all_paths() {
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec![info.path.as_str()].into_iter()))
+            .expect("unable to deserialize mask");
+    }
+    for info in Collections::all_paths() {
+        FieldMask::<Collections>::try_from(FieldMaskInput(vec![info.path.as_str()].into_iter()))
+            .expect("unable to deserialize mask");
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Clone, Default, Maskable)]
+struct Child {
+    a: u32,
+    b: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+enum OneOf {
+    A(String),
+    B(Child),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        Self::A(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+struct Parent {
+    child: Child,
+    optional_child: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+    labels: HashMap<String, String>,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    c: u32,
+}
+
+fn target() -> Parent {
+    Parent {
+        child: Child {
+            a: 1,
+            b: "b".into(),
+        },
+        optional_child: None,
+        one_of: Some(OneOf::A("a".into())),
+        labels: vec![
+            ("env".to_string(), "dev".to_string()),
+            ("team".to_string(), "core".to_string()),
+        ]
+        .into_iter()
+        .collect(),
+        items: vec![Child {
+            a: 2,
+            b: "c".into(),
+        }],
+        c: 3,
+    }
+}
+
+fn src() -> Parent {
+    Parent {
+        child: Child {
+            a: 10,
+            b: "updated b".into(),
+        },
+        optional_child: Some(Child {
+            a: 20,
+            b: "updated c".into(),
+        }),
+        one_of: Some(OneOf::B(Child {
+            a: 30,
+            b: "updated d".into(),
+        })),
+        labels: vec![("env".to_string(), "prod".to_string())]
+            .into_iter()
+            .collect(),
+        items: vec![
+            Child {
+                a: 40,
+                b: "updated e".into(),
+            },
+            Child {
+                a: 50,
+                b: "updated f".into(),
+            },
+        ],
+        c: 60,
+    }
+}
+
+fn assert_apply_ref(paths: Vec<&str>) {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(paths.into_iter()))
+        .expect("unable to deserialize mask");
+
+    let mut expected = target();
+    mask.clone().apply(&mut expected, src());
+
+    let mut target = target();
+    mask.apply_ref(&mut target, &src());
+    assert_eq!(target, expected);
+}
+
+#[test]
+fn apply_ref_struct() {
+    assert_apply_ref(vec!["child.b", "c"]);
+}
+
+#[test]
+fn apply_ref_option() {
+    assert_apply_ref(vec!["optional_child.a"]);
+    assert_apply_ref(vec!["optional_child"]);
+}
+
+#[test]
+fn apply_ref_one_of() {
+    assert_apply_ref(vec!["b.b"]);
+    assert_apply_ref(vec!["a"]);
+}
+
+#[test]
+fn apply_ref_map() {
+    assert_apply_ref(vec!["labels.env"]);
+    assert_apply_ref(vec!["labels.team"]);
+    assert_apply_ref(vec!["labels"]);
+}
+
+#[test]
+fn apply_ref_vec() {
+    assert_apply_ref(vec!["items.*.b"]);
+    assert_apply_ref(vec!["items.0.a"]);
+    assert_apply_ref(vec!["items"]);
+}
+
+#[test]
+fn apply_ref_to_many_targets() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(vec!["child.a"].into_iter()))
+        .expect("unable to deserialize mask");
+    let src = src();
+
+    let mut targets = vec![target(), target()];
+    for target in &mut targets {
+        mask.apply_ref(target, &src);
+    }
+
+    for target in targets {
+        assert_eq!(target.child.a, 10);
+        assert_eq!(target.child.b, "b");
+    }
+}
+
+/// Neither `Clone` nor `PartialEq`.
+#[derive(Debug)]
+struct Opaque(u32);
+
+#[derive(Debug, Default, Maskable)]
+struct WithOpaque {
+    opaques: Vec<Opaque>,
+    c: u32,
+}
+
+#[test]
+fn apply_without_clone() {
+    let mut target = WithOpaque {
+        opaques: vec![Opaque(1)],
+        c: 1,
+    };
+    let src = WithOpaque {
+        opaques: vec![Opaque(2), Opaque(3)],
+        c: 2,
+    };
+    FieldMask::try_from(FieldMaskInput(vec!["opaques"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut target, src);
+    assert_eq!(target.opaques.len(), 2);
+    assert_eq!(target.opaques[0].0, 2);
+    assert_eq!(target.c, 1);
+}
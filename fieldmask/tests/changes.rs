@@ -0,0 +1,109 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Clone, Default, Maskable)]
+struct Child {
+    a: u32,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+enum OneOf {
+    A(String),
+    B(Child),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        Self::A(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+struct Parent {
+    child: Child,
+    optional_child: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+    labels: HashMap<String, String>,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    c: u32,
+}
+
+fn target() -> Parent {
+    Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: None,
+        one_of: Some(OneOf::B(Child { a: 3, b: 4 })),
+        labels: vec![
+            ("env".to_string(), "dev".to_string()),
+            ("team".to_string(), "core".to_string()),
+        ]
+        .into_iter()
+        .collect(),
+        items: vec![Child { a: 5, b: 6 }, Child { a: 7, b: 8 }],
+        c: 9,
+    }
+}
+
+fn src() -> Parent {
+    Parent {
+        child: Child { a: 1, b: 20 },
+        optional_child: Some(Child { a: 30, b: 0 }),
+        one_of: Some(OneOf::B(Child { a: 3, b: 40 })),
+        labels: vec![
+            ("env".to_string(), "dev".to_string()),
+            ("region".to_string(), "eu".to_string()),
+        ]
+        .into_iter()
+        .collect(),
+        items: vec![Child { a: 5, b: 60 }],
+        c: 9,
+    }
+}
+
+fn assert_changes(paths: Vec<&str>, expected_paths: Vec<&str>) {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(paths.into_iter()))
+        .expect("unable to deserialize mask");
+
+    let mut expected = target();
+    mask.clone().apply(&mut expected, src());
+
+    let mut target = target();
+    let changes = mask.apply_with_changes(&mut target, src());
+    assert_eq!(target, expected);
+    assert_eq!(changes, FieldMask::diff(&self::target(), &target));
+    assert_eq!(changes.to_paths().unwrap(), expected_paths);
+}
+
+#[test]
+fn unchanged_leaves_are_skipped() {
+    assert_changes(vec!["child", "c"], vec!["child.b"]);
+    assert_changes(vec!["child.a"], vec![]);
+}
+
+#[test]
+fn changes_of_option() {
+    assert_changes(vec!["optional_child.a"], vec!["optional_child.a"]);
+}
+
+#[test]
+fn changes_of_one_of() {
+    assert_changes(vec!["b"], vec!["b.b"]);
+    assert_changes(vec!["a"], vec!["b"]);
+}
+
+#[test]
+fn changes_of_map() {
+    assert_changes(vec!["labels.env"], vec![]);
+    assert_changes(vec!["labels"], vec!["labels.region", "labels.team"]);
+}
+
+#[test]
+fn changes_of_vec() {
+    assert_changes(vec!["items.*.a"], vec![]);
+    assert_changes(vec!["items.0"], vec!["items.0.b"]);
+    assert_changes(vec!["items"], vec!["items"]);
+}
@@ -0,0 +1,83 @@
+use fieldmask::{DeserializeMaskErrorKind, FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Child,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+const ENTRIES: &[&str] = &[
+    "primitive",
+    "child_1.feld_two",
+    "child_2.field_one",
+    "items.x",
+    "child_2.`field_two",
+    "primitive.len",
+];
+
+#[test]
+fn collect_every_error() {
+    let errors = FieldMask::<Parent>::try_from_all(FieldMaskInput(ENTRIES.iter().copied()))
+        .expect_err("should fail to parse fieldmask");
+
+    let entries: Vec<_> = errors.iter().map(|err| err.entry.as_str()).collect();
+    assert_eq!(
+        entries,
+        vec![
+            "child_1.feld_two",
+            "items.x",
+            "child_2.`field_two",
+            "primitive.len",
+        ],
+    );
+    let kinds: Vec<_> = errors.iter().map(|err| err.kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(DeserializeMaskErrorKind::UnknownField),
+            Some(DeserializeMaskErrorKind::InvalidIndex),
+            None,
+            Some(DeserializeMaskErrorKind::BelowLeaf),
+        ],
+    );
+    assert!(errors.to_string().starts_with(
+        r#"child_1.feld_two: no field "feld_two" in Child; did you mean "field_two"?; items.x: "#
+    ));
+}
+
+#[test]
+fn all_valid() {
+    let mask = FieldMask::<Parent>::try_from_all(FieldMaskInput(
+        vec!["primitive", "child_2.field_one"].into_iter(),
+    ))
+    .expect("should parse fieldmask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["primitive", "child_2.field_one"]
+    );
+}
+
+#[test]
+fn keep_valid_entries() {
+    let (mask, errors) =
+        FieldMask::<Parent>::from_valid_entries(FieldMaskInput(ENTRIES.iter().copied()));
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["primitive", "child_2.field_one"]
+    );
+    assert_eq!(errors.len(), 4);
+
+    let (mask, errors) =
+        FieldMask::<Parent>::from_valid_entries(FieldMaskInput(vec!["child_1"].into_iter()));
+    assert_eq!(mask.to_paths().unwrap(), vec!["child_1"]);
+    assert!(errors.is_empty());
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use fieldmask::{FieldMask, Maskable};
+
+#[derive(Debug, PartialEq, Clone, Default, Maskable)]
+struct Child {
+    a: u32,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+enum OneOf {
+    A(String),
+    B(Child),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        Self::A(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Maskable)]
+struct Parent {
+    child: Child,
+    optional_child: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+    labels: HashMap<String, String>,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    c: u32,
+}
+
+fn parent() -> Parent {
+    Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: Some(Child { a: 3, b: 4 }),
+        one_of: Some(OneOf::B(Child { a: 5, b: 6 })),
+        labels: vec![("env".to_string(), "dev".to_string())]
+            .into_iter()
+            .collect(),
+        items: vec![Child { a: 7, b: 8 }, Child { a: 9, b: 10 }],
+        c: 11,
+    }
+}
+
+fn assert_diff(old: Parent, new: Parent, expected_paths: Vec<&str>) {
+    let mask = FieldMask::diff(&old, &new);
+    assert_eq!(mask.to_paths().unwrap(), expected_paths);
+
+    let mut target = old;
+    mask.apply(&mut target, new.clone());
+    assert_eq!(target, new);
+}
+
+#[test]
+fn no_change() {
+    assert_diff(parent(), parent(), vec![]);
+}
+
+#[test]
+fn nested_fields() {
+    let mut new = parent();
+    new.child.b = 20;
+    new.optional_child = Some(Child { a: 3, b: 40 });
+    new.one_of = Some(OneOf::B(Child { a: 50, b: 6 }));
+    new.c = 110;
+    assert_diff(
+        parent(),
+        new,
+        vec!["child.b", "optional_child.b", "b.a", "c"],
+    );
+}
+
+#[test]
+fn different_variant() {
+    let mut new = parent();
+    new.one_of = Some(OneOf::A("a".into()));
+    assert_diff(parent(), new, vec!["a"]);
+}
+
+#[test]
+fn options() {
+    let mut old = parent();
+    old.optional_child = None;
+    old.one_of = None;
+    assert_diff(old.clone(), parent(), vec!["optional_child", "b"]);
+    assert_diff(parent(), old, vec!["optional_child", "b"]);
+}
+
+#[test]
+fn option_against_none() {
+    let mut old = parent();
+    old.optional_child = None;
+    old.one_of = None;
+    let mut new = old.clone();
+    new.optional_child = Some(Child { a: 3, b: 0 });
+    new.one_of = Some(OneOf::B(Child::default()));
+    assert_diff(old.clone(), new.clone(), vec!["optional_child.a", "b"]);
+    assert_diff(new, old.clone(), vec!["optional_child.a", "b"]);
+
+    let mut new = old.clone();
+    new.optional_child = Some(Child::default());
+    new.one_of = Some(OneOf::default());
+    assert_diff(old.clone(), new.clone(), vec!["optional_child", "a", "b"]);
+    assert_diff(new, old, vec!["optional_child", "a", "b"]);
+}
+
+#[test]
+fn map_and_vec() {
+    let mut new = parent();
+    new.labels.insert("env".into(), "prod".into());
+    new.labels.insert("team".into(), "a".into());
+    new.items[1].b = 100;
+    assert_diff(
+        parent(),
+        new,
+        vec!["labels.env", "labels.team", "items.1.b"],
+    );
+
+    let mut new = parent();
+    new.labels.clear();
+    new.items.pop();
+    assert_diff(parent(), new, vec!["labels.env", "items"]);
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use fieldmask::{
+    DeserializeFieldMaskError, DeserializeMaskErrorKind, FieldMask, FieldMaskInput, Maskable,
+};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    labels: HashMap<String, Child>,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn error(path: &str) -> DeserializeFieldMaskError {
+    FieldMask::<Parent>::try_from(FieldMaskInput(vec!["primitive", path].into_iter()))
+        .expect_err("should fail to parse fieldmask")
+}
+
+#[test]
+fn unknown_field() {
+    let err = error("child_1.feld_two");
+    assert_eq!(err.entry, "child_1.feld_two");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::UnknownField));
+    assert_eq!(
+        err.to_string(),
+        r#"child_1.feld_two: no field "feld_two" in Child; did you mean "field_two"?"#,
+    );
+
+    let mask_err = err.mask_error().expect("should be a mask error");
+    assert_eq!(mask_err.type_str, "Child");
+    assert_eq!(mask_err.field, "feld_two");
+}
+
+#[test]
+fn unknown_field_at_root() {
+    let err = error("child_3");
+    assert_eq!(err.segment_index(), Some(0));
+    assert_eq!(
+        err.to_string(),
+        r#"child_3: no field "child_3" in Parent; did you mean "child_1"?"#,
+    );
+}
+
+#[test]
+fn unknown_field_below_flattened_field() {
+    let err = error("variant_two.field_three");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::UnknownField));
+    assert_eq!(
+        err.to_string(),
+        r#"variant_two.field_three: no field "field_three" in Child"#,
+    );
+}
+
+#[test]
+fn unknown_field_below_collections() {
+    let err = error("labels.team.field_three");
+    assert_eq!(err.segment_index(), Some(2));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::UnknownField));
+}
+
+#[test]
+fn invalid_index() {
+    let err = error("items.first");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::InvalidIndex));
+    assert_eq!(
+        err.to_string(),
+        r#"items.first: invalid index "first" in Vec"#
+    );
+}
+
+#[test]
+fn below_leaf() {
+    let err = error("child_1.field_one.length");
+    assert_eq!(err.segment_index(), Some(2));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::BelowLeaf));
+    assert_eq!(
+        err.to_string(),
+        r#"child_1.field_one.length: no field "length" in String, which has no fields"#,
+    );
+}
+
+#[test]
+fn empty_segment() {
+    let err = error("child_1..field_two");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::EmptySegment));
+    assert_eq!(
+        err.to_string(),
+        "child_1..field_two: empty segment in Child"
+    );
+
+    let err = error("primitive.");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::EmptySegment));
+}
+
+#[test]
+fn invalid_wildcard() {
+    let err = error("child_1.*.field_two");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::InvalidWildcard));
+    assert_eq!(
+        err.to_string(),
+        r#"child_1.*.field_two: unexpected "*" in Child"#,
+    );
+
+    assert!(FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["child_1.*", "items.*.field_two"].into_iter()
+    ))
+    .is_ok());
+}
+
+#[test]
+fn parse_error() {
+    let err = error("labels.`team");
+    assert!(err.parse_error().is_some());
+    assert_eq!(err.segment_index(), None);
+    assert_eq!(err.kind(), None);
+}
+
+#[test]
+fn query_error() {
+    let mask = FieldMask::<Parent>::default();
+    let err = mask
+        .contains("child_1.feld_two")
+        .expect_err("should fail to query fieldmask");
+    assert_eq!(err.segment_index(), Some(1));
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::UnknownField));
+}
+
+#[test]
+fn suggestion() {
+    let err = error("child_1.feild_two");
+    let mask_err = err.mask_error().expect("should be a mask error");
+    assert_eq!(mask_err.candidates(), vec!["field_two", "field_one"]);
+    assert_eq!(mask_err.suggestion(), Some("field_two"));
+}
+
+#[test]
+fn suggestion_from_flattened_field() {
+    let err = error("variant_tow");
+    let mask_err = err.mask_error().expect("should be a mask error");
+    assert_eq!(mask_err.suggestion(), Some("variant_two"));
+    assert_eq!(mask_err.candidates()[..2], ["variant_two", "variant_one"]);
+    assert_eq!(mask_err.candidates().len(), 6);
+}
+
+#[test]
+fn no_suggestion() {
+    let err = error("child_1.name");
+    assert_eq!(err.mask_error().and_then(|err| err.suggestion()), None);
+    assert_eq!(err.to_string(), r#"child_1.name: no field "name" in Child"#);
+}
@@ -0,0 +1,59 @@
+use std::convert::TryFrom;
+
+use fieldmask::{field_mask, FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Parent> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn field_mask_macro() {
+    assert_eq!(
+        field_mask!(Parent; primitive, child_1.field_two, variant_two),
+        mask(vec!["primitive", "child_1.field_two", "variant_two"]),
+    );
+}
+
+#[test]
+fn option_and_flattened_paths() {
+    assert_eq!(
+        field_mask!(Parent; child_2.field_one, variant_two.field_two, variant_one),
+        mask(vec![
+            "child_2.field_one",
+            "variant_two.field_two",
+            "variant_one",
+        ]),
+    );
+}
+
+#[test]
+fn empty_field_mask_macro() {
+    assert_eq!(field_mask!(Parent;), FieldMask::default());
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use fieldmask::{DeserializeMaskErrorKind, FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child: Child,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+    ports: BTreeMap<u16, String>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[test]
+fn skip_unknown_fields() {
+    let (mask, warnings) = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(
+        vec![
+            "primitive",
+            "child.field_three",
+            "new_field.a",
+            "items.*.field_one",
+        ]
+        .into_iter(),
+    ))
+    .expect("should parse fieldmask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["primitive", "items.*.field_one"]
+    );
+
+    let entries: Vec<_> = warnings.iter().map(|w| w.entry.as_str()).collect();
+    assert_eq!(entries, vec!["child.field_three", "new_field.a"]);
+    assert_eq!(warnings[1].segment_index(), Some(0));
+}
+
+#[test]
+fn no_warnings() {
+    let (mask, warnings) =
+        FieldMask::<Parent>::try_from_lenient(FieldMaskInput(vec!["child"].into_iter()))
+            .expect("should parse fieldmask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["child"]);
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn reject_other_errors() {
+    let err = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(
+        vec!["new_field", "primitive.len"].into_iter(),
+    ))
+    .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.entry, "primitive.len");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::BelowLeaf));
+
+    let err = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(vec!["child..a"].into_iter()))
+        .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::EmptySegment));
+
+    let err = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(vec!["`child"].into_iter()))
+        .expect_err("should fail to parse fieldmask");
+    assert!(err.parse_error().is_some());
+}
+
+#[test]
+fn reject_invalid_indices_and_keys() {
+    let err = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(
+        vec!["items.first.field_one"].into_iter(),
+    ))
+    .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::InvalidIndex));
+
+    let err = FieldMask::<Parent>::try_from_lenient(FieldMaskInput(vec!["ports.x"].into_iter()))
+        .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::InvalidKey));
+    assert_eq!(err.to_string(), r#"ports.x: invalid key "x" in BTreeMap"#);
+}
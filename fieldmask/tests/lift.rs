@@ -0,0 +1,61 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn child_mask(paths: Vec<&str>) -> FieldMask<Child> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn lift() {
+    let mask = FieldMask::<Parent>::from_field_child_1(child_mask(vec!["field_two"]));
+    assert_eq!(mask.to_paths().unwrap(), vec!["child_1.field_two"]);
+}
+
+#[test]
+fn lift_into_option() {
+    let mask = FieldMask::<Parent>::from_field_child_2(child_mask(vec!["field_one"]));
+    assert_eq!(mask.to_paths().unwrap(), vec!["child_2.field_one"]);
+}
+
+#[test]
+fn lift_into_variant() {
+    let variant = FieldMask::<OneOfField>::from_field_variant_two(child_mask(vec!["field_one"]));
+    let mask = FieldMask::<Parent>::from_field_one_of_field(variant);
+    assert_eq!(mask.to_paths().unwrap(), vec!["variant_two.field_one"]);
+}
+
+#[test]
+fn merge_lifted() {
+    let mut mask = FieldMask::<Parent>::try_from(FieldMaskInput(vec!["primitive"].into_iter()))
+        .expect("unable to deserialize mask");
+    mask |= FieldMask::from_field_child_1(child_mask(vec!["field_one", "field_two"]));
+    assert_eq!(mask.to_paths().unwrap(), vec!["primitive", "child_1"]);
+}
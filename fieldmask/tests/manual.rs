@@ -0,0 +1,99 @@
+use std::convert::TryFrom;
+
+use fieldmask::{
+    BitwiseWrap, DeserializeMaskError, FieldMask, FieldMaskInput, Maskable, PathInfo, Segment,
+    SelfMaskable,
+};
+
+/// Implements only the items that have no default.
+#[derive(Debug, PartialEq, Default)]
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+impl Maskable for Point {
+    type Mask = BitwiseWrap<(bool, bool)>;
+
+    const TYPE_STR: &'static str = "Point";
+
+    fn try_bitor_assign_mask(
+        mask: &mut Self::Mask,
+        field_mask_segs: &[Segment<&str>],
+    ) -> Result<(), DeserializeMaskError> {
+        match field_mask_segs {
+            [] | [Segment::Unquoted("*")] => *mask = !Self::Mask::default(),
+            [Segment::Unquoted("x")] => mask.0 .0 = true,
+            [Segment::Unquoted("y")] => mask.0 .1 = true,
+            _ => {
+                return Err(DeserializeMaskError::unknown_field(
+                    "Point",
+                    field_mask_segs[0],
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn append_all_paths(prefix: &str, paths: &mut Vec<PathInfo>) {
+        for name in ["x", "y"] {
+            paths.push(PathInfo {
+                path: if prefix.is_empty() {
+                    name.into()
+                } else {
+                    format!("{}.{}", prefix, name)
+                },
+                type_str: u32::TYPE_STR,
+            });
+        }
+    }
+}
+
+impl SelfMaskable for Point {
+    fn apply_mask(&mut self, src: Self, mask: Self::Mask) {
+        if mask.0 .0 {
+            self.x = src.x;
+        }
+        if mask.0 .1 {
+            self.y = src.y;
+        }
+    }
+
+    fn project_mask(&mut self, mask: &Self::Mask) {
+        if !mask.0 .0 {
+            self.x = 0;
+        }
+        if !mask.0 .1 {
+            self.y = 0;
+        }
+    }
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Point> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn default_paths() {
+    assert_eq!(mask(vec!["y"]).to_paths().unwrap(), vec!["y"]);
+    assert_eq!(mask(vec!["x", "y"]).to_paths().unwrap(), vec!["x", "y"]);
+    assert!(FieldMask::<Point>::default().to_paths().unwrap().is_empty());
+}
+
+#[test]
+fn default_queries() {
+    let mask = mask(vec!["x"]);
+    assert!(mask.contains("x").unwrap());
+    assert!(!mask.contains("y").unwrap());
+    assert!(!mask.contains("*").unwrap());
+    assert!(mask.intersects("*").unwrap());
+    assert!(!mask.intersects("y").unwrap());
+    assert!(mask.contains("z").is_err());
+}
+
+#[test]
+fn apply() {
+    let mut target = Point { x: 1, y: 2 };
+    mask(vec!["y"]).apply(&mut target, Point { x: 3, y: 4 });
+    assert_eq!(target, Point { x: 1, y: 4 });
+}
@@ -0,0 +1,247 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+};
+
+use fieldmask::{DeserializeMaskErrorKind, FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Region {
+    quota: u32,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    labels: HashMap<String, String>,
+    regions: BTreeMap<String, Region>,
+    c: u32,
+}
+
+fn labels(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn regions(entries: &[(&str, u32, &str)]) -> BTreeMap<String, Region> {
+    entries
+        .iter()
+        .map(|(k, quota, name)| {
+            (
+                k.to_string(),
+                Region {
+                    quota: *quota,
+                    name: name.to_string(),
+                },
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn update_entry() {
+    let mut struct1 = Parent {
+        labels: labels(&[("env", "dev"), ("team", "a")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[("env", "prod"), ("team", "b")]),
+        regions: regions(&[]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("env", "prod"), ("team", "a")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["labels.env"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn insert_and_delete_entries() {
+    let mut struct1 = Parent {
+        labels: labels(&[("env", "dev"), ("team", "a")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[("owner", "me")]),
+        regions: regions(&[]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("owner", "me"), ("team", "a")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+
+    FieldMask::try_from(FieldMaskInput(
+        vec!["labels.env", "labels.owner"].into_iter(),
+    ))
+    .expect("unable to deserialize mask")
+    .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn replace_whole_map() {
+    let mut struct1 = Parent {
+        labels: labels(&[("env", "dev"), ("team", "a")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[("owner", "me")]),
+        regions: regions(&[]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("owner", "me")]),
+        regions: regions(&[]),
+        c: 1,
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["labels"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn nested_value() {
+    let mut struct1 = Parent {
+        labels: labels(&[]),
+        regions: regions(&[("us_east", 1, "east"), ("us_west", 2, "west")]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[]),
+        regions: regions(&[
+            ("us_east", 10, "updated east"),
+            ("us_west", 20, "updated west"),
+            ("eu", 30, "eu"),
+        ]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[]),
+        regions: regions(&[
+            ("us_east", 10, "east"),
+            ("us_west", 2, "west"),
+            ("eu", 30, ""),
+        ]),
+        c: 1,
+    };
+
+    FieldMask::try_from(FieldMaskInput(
+        vec!["regions.us_east.quota", "regions.eu.quota"].into_iter(),
+    ))
+    .expect("unable to deserialize mask")
+    .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn project() {
+    let value = Parent {
+        labels: labels(&[("env", "dev"), ("team", "a")]),
+        regions: regions(&[("us_east", 1, "east"), ("us_west", 2, "west")]),
+        c: 1,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("team", "a")]),
+        regions: regions(&[("us_west", 0, "west")]),
+        c: 0,
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(
+        vec!["labels.team", "regions.us_west.name"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(mask.project(value), expected_struct);
+}
+
+#[test]
+fn to_paths() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec![
+            "labels.team",
+            "labels.env",
+            "regions.us_west.name",
+            "regions.us_west.quota",
+            "regions.eu.quota",
+        ]
+        .into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec![
+            "labels.env",
+            "labels.team",
+            "regions.eu.quota",
+            "regions.us_west"
+        ],
+    );
+}
+
+#[test]
+fn to_paths_of_excluded_keys() {
+    let parse = |paths: Vec<&str>| {
+        FieldMask::<Parent>::try_from(FieldMaskInput(paths.into_iter()))
+            .expect("unable to deserialize mask")
+    };
+
+    let mask = parse(vec!["labels"]) & !parse(vec!["labels.env"]);
+    assert!(!mask.contains("labels").unwrap());
+    assert_eq!(mask.to_paths().unwrap_err().path, "labels");
+
+    let mask = parse(vec!["regions"]) & !parse(vec!["regions.eu.quota"]);
+    assert_eq!(mask.to_paths().unwrap_err().path, "regions");
+
+    let mask = !parse(vec!["labels"]);
+    assert_eq!(mask.to_paths().unwrap(), vec!["regions", "c"]);
+}
+
+#[test]
+fn bit_operations() {
+    let parse = |paths: Vec<&str>| {
+        FieldMask::<Parent>::try_from(FieldMaskInput(paths.into_iter()))
+            .expect("unable to deserialize mask")
+    };
+
+    assert_eq!(
+        parse(vec!["labels.env", "labels.team"]) & parse(vec!["labels.team", "c"]),
+        parse(vec!["labels.team"]),
+    );
+    assert_eq!(
+        parse(vec!["labels"]) & !parse(vec!["labels.env"]) | parse(vec!["labels.env"]),
+        parse(vec!["labels"]),
+    );
+}
+
+#[test]
+fn empty_key() {
+    for path in ["labels.", "regions..quota"] {
+        let err = FieldMask::<Parent>::try_from(FieldMaskInput(vec![path].into_iter()))
+            .expect_err("should fail to parse fieldmask");
+        assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::EmptySegment));
+        assert_eq!(err.segment_index(), Some(1));
+    }
+
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(vec!["labels.``"].into_iter()))
+        .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["labels.``"]);
+}
@@ -0,0 +1,276 @@
+use std::{convert::TryFrom, mem::size_of, ops::Not};
+
+use fieldmask::{field_mask, FieldMask, FieldMaskInput, Maskable, SelfMaskable};
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+#[fieldmask(packed)]
+struct Parent {
+    primitive: String,
+    child: Child,
+    optional: Option<u32>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+    flag: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+#[fieldmask(packed)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(packed)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+#[fieldmask(packed)]
+struct Wrapper<T: SelfMaskable>
+where
+    T::Mask: Default + Not<Output = T::Mask> + PartialEq,
+{
+    value: T,
+    flag: bool,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+#[fieldmask(packed)]
+struct Wide {
+    f_00: u32,
+    f_01: u32,
+    f_02: u32,
+    f_03: u32,
+    f_04: u32,
+    f_05: u32,
+    f_06: u32,
+    f_07: u32,
+    f_08: u32,
+    f_09: u32,
+    f_10: u32,
+    f_11: u32,
+    f_12: u32,
+    f_13: u32,
+    f_14: u32,
+    f_15: u32,
+    f_16: u32,
+    f_17: u32,
+    f_18: u32,
+    f_19: u32,
+    f_20: u32,
+    f_21: u32,
+    f_22: u32,
+    f_23: u32,
+    f_24: u32,
+    f_25: u32,
+    f_26: u32,
+    f_27: u32,
+    f_28: u32,
+    f_29: u32,
+    f_30: u32,
+    f_31: u32,
+    f_32: u32,
+    f_33: u32,
+    f_34: u32,
+    f_35: u32,
+    f_36: u32,
+    f_37: u32,
+    f_38: u32,
+    f_39: u32,
+    f_40: u32,
+    f_41: u32,
+    f_42: u32,
+    f_43: u32,
+    f_44: u32,
+    f_45: u32,
+    f_46: u32,
+    f_47: u32,
+    f_48: u32,
+    f_49: u32,
+    f_50: u32,
+    f_51: u32,
+    f_52: u32,
+    f_53: u32,
+    f_54: u32,
+    f_55: u32,
+    f_56: u32,
+    f_57: u32,
+    f_58: u32,
+    f_59: u32,
+    f_60: u32,
+    f_61: u32,
+    f_62: u32,
+    f_63: u32,
+    f_64: u32,
+    f_65: u32,
+    f_66: u32,
+    f_67: u32,
+    f_68: u32,
+    f_69: u32,
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Parent> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+fn parent() -> Parent {
+    Parent {
+        primitive: "primitive".into(),
+        child: Child {
+            field_one: "one".into(),
+            field_two: 2,
+        },
+        optional: Some(3),
+        one_of_field: Some(OneOfField::VariantOne("variant".into())),
+        flag: true,
+    }
+}
+
+#[test]
+fn layout() {
+    assert_eq!(size_of::<<Child as Maskable>::Mask>(), 8);
+    assert_eq!(size_of::<<Wide as Maskable>::Mask>(), 16);
+}
+
+#[test]
+fn paths() {
+    let mask = mask(vec![
+        "flag",
+        "child.field_one",
+        "child.field_two",
+        "variant_one",
+    ]);
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["child", "variant_one", "flag"]
+    );
+    assert!(mask.contains("child.field_two").unwrap());
+    assert!(!mask.contains("optional").unwrap());
+    assert!(mask.intersects("child").unwrap());
+
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec!["flag.x"].into_iter()))
+            .expect_err("should fail to parse fieldmask")
+            .to_string(),
+        r#"flag.x: no field "x" in bool, which has no fields"#,
+    );
+}
+
+#[test]
+fn apply() {
+    let mut target = Parent::default();
+    let changes = mask(vec!["primitive", "optional", "child.field_two"])
+        .apply_with_changes(&mut target, parent());
+    assert_eq!(
+        target,
+        Parent {
+            primitive: "primitive".into(),
+            child: Child {
+                field_one: String::new(),
+                field_two: 2,
+            },
+            optional: Some(3),
+            ..Parent::default()
+        },
+    );
+    assert_eq!(
+        changes.to_paths().unwrap(),
+        vec!["primitive", "child.field_two", "optional"],
+    );
+    assert_eq!(
+        FieldMask::diff(&target, &parent()).to_paths().unwrap(),
+        vec!["child.field_one", "variant_one", "flag"],
+    );
+
+    let projected = mask(vec!["flag", "child.field_one"]).project(parent());
+    assert_eq!(
+        projected,
+        Parent {
+            child: Child {
+                field_one: "one".into(),
+                field_two: 0,
+            },
+            flag: true,
+            ..Parent::default()
+        },
+    );
+}
+
+#[test]
+fn sub_masks() {
+    let mut mask = mask(vec!["child.field_two", "optional"]);
+    assert!(mask.field_optional().is_full());
+    assert!(mask.field_flag().is_empty());
+    assert_eq!(mask.field_child().to_paths().unwrap(), vec!["field_two"]);
+    assert!(mask.field_child().field_field_two().is_full());
+    assert!(mask.field_one_of_field().field_variant_one().is_empty());
+
+    *mask.field_flag_mut() = !FieldMask::default();
+    *mask.field_optional_mut() = FieldMask::default();
+    *mask.field_child_mut().field_field_one_mut() = !FieldMask::default();
+    assert!(mask.field_flag().is_full());
+    assert_eq!(mask.to_paths().unwrap(), vec!["child", "flag"]);
+
+    let lifted = FieldMask::<Parent>::from_field_flag(!FieldMask::<bool>::default());
+    assert_eq!(lifted.to_paths().unwrap(), vec!["flag"]);
+    assert_eq!(
+        field_mask!(Parent; flag, child.field_one)
+            .to_paths()
+            .unwrap(),
+        vec!["child.field_one", "flag"],
+    );
+}
+
+#[test]
+fn generic() {
+    let mask = FieldMask::<Wrapper<Child>>::try_from(FieldMaskInput(
+        vec!["flag", "value.field_one"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert!(mask.field_flag().is_full());
+    assert_eq!(mask.field_value().to_paths().unwrap(), vec!["field_one"]);
+}
+
+#[test]
+fn wide() {
+    let mask = FieldMask::<Wide>::try_from(FieldMaskInput(
+        vec!["f_00", "f_63", "f_64", "f_69"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["f_00", "f_63", "f_64", "f_69"]
+    );
+
+    let mut target = Wide::default();
+    mask.apply(
+        &mut target,
+        Wide {
+            f_63: 1,
+            f_64: 2,
+            f_65: 3,
+            ..Wide::default()
+        },
+    );
+    assert_eq!(
+        target,
+        Wide {
+            f_63: 1,
+            f_64: 2,
+            ..Wide::default()
+        },
+    );
+    assert_eq!(
+        (!FieldMask::<Wide>::default()).to_paths().unwrap().len(),
+        70
+    );
+}
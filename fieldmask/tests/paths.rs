@@ -0,0 +1,129 @@
+use std::convert::TryFrom;
+
+use fieldmask::{field_mask, FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Option<Child>,
+    #[fieldmask(elements)]
+    children: Vec<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Parent> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn typed_paths() {
+    let mut mask = FieldMask::<Parent>::default();
+    mask.insert(Parent::paths().primitive());
+    mask.insert(Parent::paths().child_1().field_two());
+    mask.insert(Parent::paths().child_2());
+    mask.insert(Parent::paths().variant_two().field_one());
+
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec![
+            "primitive",
+            "child_1.field_two",
+            "child_2",
+            "variant_two.field_one",
+        ],
+    );
+}
+
+#[test]
+fn typed_path_into_mask() {
+    assert_eq!(
+        FieldMask::from(Parent::paths().child_1().field_two()),
+        mask(vec!["child_1.field_two"]),
+    );
+}
+
+#[test]
+fn typed_paths_of_elements() {
+    let mut mask = FieldMask::<Parent>::default();
+    mask.insert(Parent::paths().children().each().field_one());
+    mask.insert(Parent::paths().children().at(2).field_two());
+
+    assert_eq!(
+        mask,
+        self::mask(vec!["children.*.field_one", "children.2.field_two"]),
+    );
+}
+
+#[test]
+fn typed_paths_of_whole_vector() {
+    assert_eq!(
+        FieldMask::from(Parent::paths().children()),
+        mask(vec!["children"]),
+    );
+    assert_eq!(
+        field_mask!(Parent; children.each.field_one),
+        mask(vec!["children.*.field_one"]),
+    );
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct TwoOneOfs {
+    name: String,
+    #[fieldmask(flatten)]
+    first: Option<OneOfField>,
+    #[fieldmask(flatten)]
+    second: Option<OtherOneOf>,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OtherOneOf {
+    OtherOne(u32),
+    OtherTwo(Child),
+}
+
+impl Default for OtherOneOf {
+    fn default() -> Self {
+        Self::OtherOne(0)
+    }
+}
+
+#[test]
+fn typed_paths_of_every_flattened_field() {
+    let mut mask = FieldMask::<TwoOneOfs>::default();
+    mask.insert(TwoOneOfs::paths().name());
+    mask.insert(TwoOneOfs::paths().variant_two().field_one());
+    mask.insert(TwoOneOfs::paths().other_two().field_two());
+
+    assert_eq!(
+        mask,
+        FieldMask::try_from(FieldMaskInput(
+            vec!["name", "variant_two.field_one", "other_two.field_two"].into_iter(),
+        ))
+        .expect("unable to deserialize mask"),
+    );
+    assert_eq!(
+        field_mask!(TwoOneOfs; variant_one, other_one),
+        FieldMask::try_from(FieldMaskInput(vec!["variant_one", "other_one"].into_iter()))
+            .expect("unable to deserialize mask"),
+    );
+}
@@ -0,0 +1,93 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    a: u32,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOf {
+    A(String),
+    B(Child),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        Self::A(String::default())
+    }
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    child: Child,
+    optional_child: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+    c: u32,
+}
+
+#[test]
+fn project() {
+    let value = Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: Some(Child { a: 3, b: 4 }),
+        one_of: Some(OneOf::B(Child { a: 5, b: 6 })),
+        c: 7,
+    };
+
+    let expected_struct = Parent {
+        child: Child { a: 0, b: 2 },
+        optional_child: Some(Child { a: 3, b: 0 }),
+        one_of: Some(OneOf::B(Child { a: 5, b: 0 })),
+        c: 0,
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(
+        vec!["child.b", "optional_child.a", "b.a"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(mask.project(value), expected_struct);
+}
+
+#[test]
+fn unmasked_option_becomes_none() {
+    let value = Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: Some(Child { a: 3, b: 4 }),
+        one_of: Some(OneOf::B(Child { a: 5, b: 6 })),
+        c: 7,
+    };
+
+    let expected_struct = Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: None,
+        one_of: None,
+        c: 7,
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(vec!["child", "a", "c"].into_iter()))
+        .expect("unable to deserialize mask");
+    assert_eq!(mask.project(value), expected_struct);
+}
+
+#[test]
+fn full_mask() {
+    let value = Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: None,
+        one_of: Some(OneOf::A("a".into())),
+        c: 7,
+    };
+
+    let expected_struct = Parent {
+        child: Child { a: 1, b: 2 },
+        optional_child: None,
+        one_of: Some(OneOf::A("a".into())),
+        c: 7,
+    };
+
+    assert_eq!((!FieldMask::default()).project(value), expected_struct);
+}
@@ -92,3 +92,46 @@ fn prost() {
 
     assert_eq!(target_struct, expected_struct);
 }
+
+#[test]
+fn from_prost_field_mask() {
+    let expected_mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["primitive", "child_1.field_two", "variant_two"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    let mask = FieldMask::<Parent>::try_from(prost_types::FieldMask {
+        paths: vec![
+            "primitive".into(),
+            "child_1.field_two".into(),
+            "variant_two".into(),
+        ],
+    })
+    .expect("unable to deserialize mask");
+    assert_eq!(mask, expected_mask);
+}
+
+#[test]
+fn from_invalid_prost_field_mask() {
+    assert_eq!(
+        FieldMask::<Parent>::try_from(prost_types::FieldMask {
+            paths: vec!["primitive".into(), "child_1.field_three".into()],
+        })
+        .expect_err("should fail to parse fieldmask")
+        .entry,
+        "child_1.field_three",
+    );
+}
+
+#[test]
+fn into_prost_field_mask() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["child_1.field_one", "child_1.field_two", "variant_one"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(
+        prost_types::FieldMask::try_from(mask).unwrap().paths,
+        vec!["child_1", "variant_one"],
+    );
+}
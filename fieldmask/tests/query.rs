@@ -0,0 +1,134 @@
+use std::{collections::HashMap, convert::TryFrom};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Child,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+    labels: HashMap<String, String>,
+    #[fieldmask(elements)]
+    items: Vec<Child>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Parent> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn contains() {
+    let mask = mask(vec![
+        "child_1.field_two",
+        "child_2",
+        "variant_two.field_one",
+        "labels.env",
+        "items.*.field_one",
+    ]);
+
+    for path in &[
+        "child_1.field_two",
+        "child_2",
+        "child_2.field_one",
+        "variant_two.field_one",
+        "labels.env",
+        "items.*.field_one",
+        "items.3.field_one",
+    ] {
+        assert!(mask.contains(path).expect("invalid path"), "{}", path);
+    }
+    for path in &[
+        "primitive",
+        "child_1",
+        "child_1.field_one",
+        "variant_one",
+        "variant_two",
+        "labels",
+        "labels.team",
+        "items",
+        "items.*",
+        "items.0.field_two",
+    ] {
+        assert!(!mask.contains(path).expect("invalid path"), "{}", path);
+    }
+}
+
+#[test]
+fn intersects() {
+    let mask = mask(vec![
+        "child_1.field_two",
+        "variant_two.field_one",
+        "labels.env",
+        "items.2.field_one",
+    ]);
+
+    for path in &[
+        "child_1",
+        "child_1.field_two",
+        "variant_two",
+        "labels",
+        "items",
+        "items.*.field_one",
+        "items.2",
+    ] {
+        assert!(mask.intersects(path).expect("invalid path"), "{}", path);
+    }
+    for path in &[
+        "primitive",
+        "child_1.field_one",
+        "child_2",
+        "variant_one",
+        "labels.team",
+        "items.1",
+        "items.*.field_two",
+    ] {
+        assert!(!mask.intersects(path).expect("invalid path"), "{}", path);
+    }
+}
+
+#[test]
+fn invalid_path() {
+    let mask = mask(vec!["child_2"]);
+
+    assert_eq!(
+        mask.contains("child_2.field_three")
+            .expect_err("should fail to parse path")
+            .entry,
+        "child_2.field_three",
+    );
+    assert_eq!(
+        mask.intersects("variant_three")
+            .expect_err("should fail to parse path")
+            .entry,
+        "variant_three",
+    );
+}
+
+#[test]
+fn empty_and_full() {
+    assert!(FieldMask::<Parent>::default().is_empty());
+    assert!(!FieldMask::<Parent>::default().is_full());
+    assert!(!mask(vec!["child_1"]).is_empty());
+    assert!(!mask(vec!["child_1"]).is_full());
+    assert!(mask(vec!["*"]).is_full());
+}
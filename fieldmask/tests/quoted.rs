@@ -0,0 +1,147 @@
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use fieldmask::{
+    parse_path, DeserializeMaskErrorKind, FieldMask, FieldMaskInput, Maskable, ParsePathError,
+    Segment,
+};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    labels: BTreeMap<String, String>,
+    c: u32,
+}
+
+fn labels(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+    entries
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn parse() {
+    assert_eq!(
+        parse_path("labels.`example.com/team`").expect("unable to parse path"),
+        vec![
+            Segment::Unquoted("labels".to_string()),
+            Segment::Quoted("example.com/team".to_string()),
+        ],
+    );
+    assert_eq!(
+        parse_path("labels.`a``b`.c").expect("unable to parse path"),
+        vec![
+            Segment::Unquoted("labels".to_string()),
+            Segment::Quoted("a`b".to_string()),
+            Segment::Unquoted("c".to_string()),
+        ],
+    );
+    assert_eq!(
+        parse_path("labels.``").expect("unable to parse path"),
+        vec![
+            Segment::Unquoted("labels".to_string()),
+            Segment::Quoted(String::new()),
+        ],
+    );
+    assert_eq!(
+        parse_path("labels.`*`").expect("unable to parse path"),
+        vec![
+            Segment::Unquoted("labels".to_string()),
+            Segment::Quoted("*".to_string()),
+        ],
+    );
+}
+
+#[test]
+fn parse_errors() {
+    assert_eq!(
+        parse_path("labels.`example.com/team"),
+        Err(ParsePathError::UnterminatedQuote { position: 7 }),
+    );
+    assert_eq!(
+        parse_path("labels.`example`s.c"),
+        Err(ParsePathError::UnexpectedCharacter { position: 16 }),
+    );
+}
+
+#[test]
+fn quoted_key() {
+    let mut struct1 = Parent {
+        labels: labels(&[("example.com/team", "a"), ("env", "dev")]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[("example.com/team", "b"), ("env", "prod")]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("example.com/team", "b"), ("env", "dev")]),
+        c: 1,
+    };
+
+    FieldMask::try_from(FieldMaskInput(
+        vec!["labels.`example.com/team`"].into_iter(),
+    ))
+    .expect("unable to deserialize mask")
+    .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn quoted_wildcard() {
+    let mut struct1 = Parent {
+        labels: labels(&[("*", "a"), ("env", "dev")]),
+        c: 1,
+    };
+    let struct2 = Parent {
+        labels: labels(&[("*", "b"), ("env", "prod")]),
+        c: 2,
+    };
+
+    let expected_struct = Parent {
+        labels: labels(&[("*", "b"), ("env", "dev")]),
+        c: 1,
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(vec!["labels.`*`"].into_iter()))
+        .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["labels.`*`"]);
+    mask.apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn quoted_field() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(vec!["`c`"].into_iter()))
+        .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["c"]);
+
+    let err = FieldMask::<Parent>::try_from(FieldMaskInput(vec!["`*`"].into_iter()))
+        .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.kind(), Some(DeserializeMaskErrorKind::UnknownField));
+}
+
+#[test]
+fn unterminated_quote() {
+    let err = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["c", "labels.`example.com/team"].into_iter(),
+    ))
+    .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.entry, "labels.`example.com/team");
+    assert_eq!(
+        err.to_string(),
+        "labels.`example.com/team: unterminated quoted segment starting at 7",
+    );
+}
+
+#[test]
+fn to_paths() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["labels.env", "labels.`example.com/team`", "labels.`a``b`"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["labels.`a``b`", "labels.env", "labels.`example.com/team`"],
+    );
+}
@@ -0,0 +1,168 @@
+use std::{convert::TryFrom, rc::Rc, sync::Arc};
+
+use fieldmask::{field_mask, FieldMask, FieldMaskInput, Maskable, PathInfo};
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Node {
+    value: String,
+    child: Option<Box<Node>>,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Maskable)]
+struct Shared {
+    rc: Rc<Leaf>,
+    arc: Arc<Leaf>,
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Maskable)]
+struct Leaf {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Outer {
+    node: other::Node,
+}
+
+mod other {
+    use fieldmask::Maskable;
+
+    /// Named like the recursive `Node`, but a different type.
+    #[derive(Debug, PartialEq, Default, Maskable)]
+    pub(crate) struct Node {
+        pub(crate) boxed: Box<super::Node>,
+    }
+}
+
+fn node(values: &[&str]) -> Option<Box<Node>> {
+    values.split_first().map(|(value, rest)| {
+        Box::new(Node {
+            value: (*value).into(),
+            child: node(rest),
+        })
+    })
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Node> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn paths() {
+    let mask = mask(vec!["child.child.value", "child.child.child.child"]);
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["child.child.value", "child.child.child.child"],
+    );
+    assert!(mask.contains("child.child.child.child.value").unwrap());
+    assert!(!mask.contains("child.value").unwrap());
+    assert!(mask.intersects("child").unwrap());
+
+    let err = FieldMask::<Node>::try_from(FieldMaskInput(vec!["child.child.vale"].into_iter()))
+        .expect_err("should fail to parse fieldmask");
+    assert_eq!(err.segment_index(), Some(2));
+
+    assert_eq!(
+        field_mask!(Node; child.child.value, child.child.child.child),
+        mask,
+    );
+    assert_eq!(
+        (!FieldMask::<Node>::default()).to_paths().unwrap(),
+        vec!["value", "child"]
+    );
+}
+
+#[test]
+fn paths_of_empty_children() {
+    assert!(FieldMask::<Node>::default().to_paths().unwrap().is_empty());
+    assert_eq!(mask(vec!["value"]).to_paths().unwrap(), vec!["value"]);
+    assert_eq!(
+        mask(vec!["child.value"]).to_paths().unwrap(),
+        vec!["child.value"]
+    );
+}
+
+#[test]
+fn apply() {
+    let mut target = *node(&["a", "b", "c"]).unwrap();
+    let src = *node(&["d", "e", "f", "g"]).unwrap();
+
+    let changes =
+        mask(vec!["child.child.value", "child.child.child"]).apply_with_changes(&mut target, src);
+    assert_eq!(target, *node(&["a", "b", "f", "g"]).unwrap());
+    assert_eq!(
+        changes.to_paths().unwrap(),
+        vec!["child.child.value", "child.child.child.value"]
+    );
+
+    assert_eq!(
+        FieldMask::diff(&target, &*node(&["a", "x", "f"]).unwrap())
+            .to_paths()
+            .unwrap(),
+        vec!["child.value", "child.child.child.value"],
+    );
+
+    let projected = mask(vec!["child.child.value"]).project(target);
+    assert_eq!(
+        projected,
+        Node {
+            value: String::new(),
+            child: Some(Box::new(Node {
+                value: String::new(),
+                child: node(&["f"]),
+            })),
+        },
+    );
+}
+
+#[test]
+fn shared() {
+    let mut target = Shared::default();
+    let src = Shared {
+        rc: Rc::new(Leaf {
+            field_one: "one".into(),
+            field_two: 1,
+        }),
+        arc: Arc::new(Leaf {
+            field_one: "two".into(),
+            field_two: 2,
+        }),
+    };
+    let mask =
+        FieldMask::<Shared>::try_from(FieldMaskInput(vec!["rc.field_one", "arc"].into_iter()))
+            .expect("unable to deserialize mask");
+    mask.apply_ref(&mut target, &src);
+    assert_eq!(
+        target,
+        Shared {
+            rc: Rc::new(Leaf {
+                field_one: "one".into(),
+                field_two: 0,
+            }),
+            arc: src.arc.clone(),
+        },
+    );
+    assert_eq!(
+        FieldMask::diff(&target, &src).to_paths().unwrap(),
+        vec!["rc.field_two"]
+    );
+}
+
+#[test]
+fn all_paths() {
+    let paths: Vec<_> = Node::all_paths()
+        .into_iter()
+        .map(|PathInfo { path, .. }| path)
+        .collect();
+    assert_eq!(paths, vec!["value", "child"]);
+}
+
+#[test]
+fn all_paths_of_same_names() {
+    let paths: Vec<_> = Outer::all_paths()
+        .into_iter()
+        .map(|PathInfo { path, .. }| path)
+        .collect();
+    assert_eq!(paths, vec!["node", "node.boxed"],);
+}
@@ -0,0 +1,99 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(rename_all = "camelCase")]
+struct Parent {
+    display_name: String,
+    #[fieldmask(rename = "legacy_child")]
+    child: Child,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(rename_all = "kebab-case")]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(rename_all = "SCREAMING_SNAKE_CASE")]
+enum OneOf {
+    VariantOne(String),
+    #[fieldmask(rename = "second")]
+    VariantTwo(u32),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+#[test]
+fn rename() {
+    let mut struct1 = Parent {
+        display_name: "name".into(),
+        child: Child {
+            field_one: "one".into(),
+            field_two: 2,
+        },
+        one_of: Some(OneOf::VariantOne("variant one".into())),
+    };
+    let struct2 = Parent {
+        display_name: "updated name".into(),
+        child: Child {
+            field_one: "updated one".into(),
+            field_two: 20,
+        },
+        one_of: Some(OneOf::VariantTwo(50)),
+    };
+
+    let expected_struct = Parent {
+        display_name: "updated name".into(),
+        child: Child {
+            field_one: "one".into(),
+            field_two: 20,
+        },
+        one_of: Some(OneOf::VariantTwo(50)),
+    };
+
+    FieldMask::try_from(FieldMaskInput(
+        vec!["displayName", "legacy_child.field-two", "second"].into_iter(),
+    ))
+    .expect("unable to deserialize mask")
+    .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn original_names_are_rejected() {
+    for path in &[
+        "display_name",
+        "child",
+        "legacy_child.field_two",
+        "variant_two",
+    ] {
+        assert_eq!(
+            FieldMask::<Parent>::try_from(FieldMaskInput(vec![*path].into_iter()))
+                .expect_err("should fail to parse fieldmask")
+                .entry,
+            *path,
+        );
+    }
+}
+
+#[test]
+fn to_paths() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["displayName", "legacy_child.field-one", "VARIANT_ONE"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["displayName", "legacy_child.field-one", "VARIANT_ONE"],
+    );
+}
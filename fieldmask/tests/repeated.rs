@@ -0,0 +1,244 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Item {
+    name: String,
+    count: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    #[fieldmask(elements)]
+    items: Vec<Item>,
+    #[fieldmask(elements)]
+    tags: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Shade {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Palette {
+    shades: Vec<Shade>,
+    name: String,
+}
+
+fn items(entries: &[(&str, u32)]) -> Vec<Item> {
+    entries
+        .iter()
+        .map(|(name, count)| Item {
+            name: name.to_string(),
+            count: *count,
+        })
+        .collect()
+}
+
+#[test]
+fn replace_whole_vector() {
+    let mut struct1 = Parent {
+        items: items(&[("a", 1), ("b", 2)]),
+        tags: vec!["x".into()],
+    };
+    let struct2 = Parent {
+        items: items(&[("c", 3)]),
+        tags: vec!["y".into()],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("c", 3)]),
+        tags: vec!["y".into()],
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["items", "tags.*"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn every_element() {
+    let mut struct1 = Parent {
+        items: items(&[("a", 1), ("b", 2), ("c", 3)]),
+        tags: vec![],
+    };
+    let struct2 = Parent {
+        items: items(&[("updated a", 10), ("updated b", 20)]),
+        tags: vec![],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("updated a", 1), ("updated b", 2), ("c", 3)]),
+        tags: vec![],
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["items.*.name"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn single_element() {
+    let mut struct1 = Parent {
+        items: items(&[("a", 1), ("b", 2)]),
+        tags: vec!["x".into(), "y".into()],
+    };
+    let struct2 = Parent {
+        items: items(&[("updated a", 10), ("updated b", 20)]),
+        tags: vec!["updated x".into(), "updated y".into()],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("updated a", 1), ("updated b", 20)]),
+        tags: vec!["x".into(), "updated y".into()],
+    };
+
+    FieldMask::try_from(FieldMaskInput(
+        vec!["items.*.name", "items.1.count", "tags.1"].into_iter(),
+    ))
+    .expect("unable to deserialize mask")
+    .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn project() {
+    let value = Parent {
+        items: items(&[("a", 1), ("b", 2)]),
+        tags: vec!["x".into()],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("a", 0), ("b", 2)]),
+        tags: vec![],
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(
+        vec!["items.*.name", "items.1.count"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(mask.project(value), expected_struct);
+}
+
+#[test]
+fn to_paths() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["items.*.name", "items.3.name", "tags"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["items.*.name", "tags"]);
+}
+
+#[test]
+fn invalid_index() {
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec!["items.first.name"].into_iter()))
+            .expect_err("should fail to parse fieldmask")
+            .entry,
+        "items.first.name",
+    );
+}
+
+#[test]
+fn round_trip() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["items.*.name", "items.*.count", "items.1.name"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["items.*.*"]);
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(
+            mask.to_paths().unwrap().iter().map(String::as_str),
+        ))
+        .expect("unable to deserialize mask"),
+        mask,
+    );
+
+    let mut struct1 = Parent {
+        items: items(&[("a", 1), ("b", 2)]),
+        tags: vec![],
+    };
+    let struct2 = Parent {
+        items: items(&[("updated a", 10)]),
+        tags: vec![],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("updated a", 10), ("b", 2)]),
+        tags: vec![],
+    };
+
+    mask.apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn complement() {
+    let parse = |paths: Vec<&str>| {
+        FieldMask::<Parent>::try_from(FieldMaskInput(paths.into_iter()))
+            .expect("unable to deserialize mask")
+    };
+
+    let mask = !parse(vec!["items.1.name"]);
+    assert_eq!(mask.to_paths().unwrap_err().path, "items");
+
+    let mut struct1 = Parent {
+        items: items(&[("a", 1), ("b", 2)]),
+        tags: vec!["x".into()],
+    };
+    let struct2 = Parent {
+        items: items(&[("updated a", 10), ("updated b", 20), ("c", 30)]),
+        tags: vec!["y".into()],
+    };
+
+    let expected_struct = Parent {
+        items: items(&[("updated a", 10), ("b", 20)]),
+        tags: vec!["y".into()],
+    };
+
+    mask.apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+
+    let mask = !parse(vec!["items.*.name", "tags"]);
+    assert_eq!(mask.to_paths().unwrap(), vec!["items.*.count"]);
+    assert_eq!(
+        parse(
+            mask.to_paths()
+                .unwrap()
+                .iter()
+                .map(String::as_str)
+                .collect()
+        ),
+        mask,
+    );
+}
+
+#[test]
+fn replace_non_maskable_elements() {
+    let mut struct1 = Palette {
+        shades: vec![Shade::Light, Shade::Dark],
+        name: "a".into(),
+    };
+    let struct2 = Palette {
+        shades: vec![Shade::Dark],
+        name: "b".into(),
+    };
+
+    let expected_struct = Palette {
+        shades: vec![Shade::Dark],
+        name: "a".into(),
+    };
+
+    let mask = FieldMask::try_from(FieldMaskInput(vec!["shades"].into_iter()))
+        .expect("unable to deserialize mask");
+    assert_eq!(mask.to_paths().unwrap(), vec!["shades"]);
+    mask.apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+
+    assert!(FieldMask::<Palette>::try_from(FieldMaskInput(vec!["shades.0"].into_iter())).is_err());
+}
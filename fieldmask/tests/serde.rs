@@ -0,0 +1,172 @@
+#![cfg(feature = "serde")]
+
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    display_name: String,
+    child_1: Child,
+    child_2: Child,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[test]
+fn serialize() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["display_name", "child_1.field_two", "child_2"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(
+        serde_json::to_string(&mask).expect("unable to serialize mask"),
+        r#""displayName,child1.fieldTwo,child2""#,
+    );
+}
+
+#[test]
+fn deserialize() {
+    let expected_mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["display_name", "child_1.field_two"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(
+        serde_json::from_str::<FieldMask<Parent>>(r#""displayName,child1.fieldTwo""#)
+            .expect("unable to deserialize mask"),
+        expected_mask,
+    );
+}
+
+#[test]
+fn deserialize_empty() {
+    assert_eq!(
+        serde_json::from_str::<FieldMask<Parent>>(r#""""#).expect("unable to deserialize mask"),
+        FieldMask::default(),
+    );
+}
+
+#[test]
+fn deserialize_invalid() {
+    serde_json::from_str::<FieldMask<Parent>>(r#""child1.fieldThree""#)
+        .expect_err("should fail to parse fieldmask");
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Labeled {
+    resource_labels: std::collections::BTreeMap<String, String>,
+}
+
+#[test]
+fn quoted_segments() {
+    let mask = FieldMask::<Labeled>::try_from(FieldMaskInput(
+        vec!["resource_labels.`example.com/Team,Name`"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    let json = serde_json::to_string(&mask).expect("unable to serialize mask");
+
+    assert_eq!(json, r#""resourceLabels.`example.com/Team,Name`""#);
+    assert_eq!(
+        serde_json::from_str::<FieldMask<Labeled>>(&json).expect("unable to deserialize mask"),
+        mask,
+    );
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(rename_all = "camelCase")]
+struct Renamed {
+    display_name: String,
+    #[fieldmask(rename = "legacyName")]
+    name: String,
+    #[fieldmask(rename = "old_child")]
+    child: Child,
+    #[fieldmask(flatten)]
+    one_of: Option<OneOf>,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+#[fieldmask(rename_all = "kebab-case")]
+enum OneOf {
+    VariantOne(String),
+    VariantTwo(u32),
+}
+
+impl Default for OneOf {
+    fn default() -> Self {
+        OneOf::VariantOne(String::new())
+    }
+}
+
+#[test]
+fn renamed_fields() {
+    let mask = FieldMask::<Renamed>::try_from(FieldMaskInput(
+        vec![
+            "displayName",
+            "legacyName",
+            "old_child.field_one",
+            "variant-two",
+        ]
+        .into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    let json = serde_json::to_string(&mask).expect("unable to serialize mask");
+
+    assert_eq!(
+        json,
+        r#""displayName,legacyName,oldChild.fieldOne,variantTwo""#
+    );
+    assert_eq!(
+        serde_json::from_str::<FieldMask<Renamed>>(&json).expect("unable to deserialize mask"),
+        mask,
+    );
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Colliding {
+    field_one: String,
+    #[fieldmask(rename = "fieldOne")]
+    other: String,
+}
+
+#[test]
+fn serialize_colliding_names() {
+    let mask = FieldMask::<Colliding>::try_from(FieldMaskInput(vec!["fieldOne"].into_iter()))
+        .expect("unable to deserialize mask");
+
+    serde_json::to_string(&mask).expect_err("should fail to serialize mask");
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Regions {
+    region_quotas: std::collections::BTreeMap<String, Child>,
+}
+
+#[test]
+fn map_keys() {
+    let mask = FieldMask::<Regions>::try_from(FieldMaskInput(
+        vec![
+            "region_quotas.usEast.field_one",
+            "region_quotas.Env",
+            "region_quotas.env1",
+            "region_quotas.env_2.field_two",
+        ]
+        .into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    let json = serde_json::to_string(&mask).expect("unable to serialize mask");
+
+    assert_eq!(
+        json,
+        r#""regionQuotas.Env,regionQuotas.env1,regionQuotas.env_2.fieldTwo,regionQuotas.usEast.fieldOne""#,
+    );
+    assert_eq!(
+        serde_json::from_str::<FieldMask<Regions>>(&json).expect("unable to deserialize mask"),
+        mask,
+    );
+}
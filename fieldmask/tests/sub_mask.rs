@@ -0,0 +1,145 @@
+use std::{convert::TryFrom, ops::Not};
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable, SelfMaskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Option<Child>,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(Child),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+fn mask(paths: Vec<&str>) -> FieldMask<Parent> {
+    FieldMask::try_from(FieldMaskInput(paths.into_iter())).expect("unable to deserialize mask")
+}
+
+#[test]
+fn sub_mask() {
+    let mask = mask(vec![
+        "child_1.field_two",
+        "child_2",
+        "variant_two.field_one",
+    ]);
+
+    assert_eq!(mask.field_child_1().to_paths().unwrap(), vec!["field_two"]);
+    assert_eq!(
+        mask.field_child_2().to_paths().unwrap(),
+        vec!["field_one", "field_two"]
+    );
+    assert!(mask.field_child_2().field_field_one().is_full());
+    assert!(mask.field_primitive().is_empty());
+    assert!(mask.field_one_of_field().field_variant_one().is_empty());
+    assert_eq!(
+        mask.field_one_of_field()
+            .field_variant_two()
+            .to_paths()
+            .unwrap(),
+        vec!["field_one"],
+    );
+}
+
+#[test]
+fn apply_sub_mask() {
+    let mask = mask(vec!["child_1.field_two"]);
+
+    let mut child = Child {
+        field_one: "one".into(),
+        field_two: 2,
+    };
+    let src = Child {
+        field_one: "updated one".into(),
+        field_two: 20,
+    };
+
+    let expected_child = Child {
+        field_one: "one".into(),
+        field_two: 20,
+    };
+
+    mask.field_child_1().apply(&mut child, src);
+    assert_eq!(child, expected_child);
+}
+
+#[test]
+fn sub_mask_mut() {
+    let mut mask = mask(vec!["primitive"]);
+    mask.field_child_1_mut()
+        .try_bitor_assign(&["field_one"])
+        .expect("unable to deserialize mask");
+    *mask.field_primitive_mut() = FieldMask::default();
+
+    assert_eq!(mask.to_paths().unwrap(), vec!["child_1.field_one"]);
+}
+
+#[derive(Debug, PartialEq, Default, Maskable)]
+struct Wrapper<T: SelfMaskable>
+where
+    T::Mask: Default + Not<Output = T::Mask> + PartialEq,
+{
+    inner: T,
+    name: String,
+}
+
+#[test]
+fn generic_sub_mask() {
+    let mask = FieldMask::<Wrapper<Child>>::try_from(FieldMaskInput(
+        vec!["inner.field_one", "name"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(mask.field_inner().to_paths().unwrap(), vec!["field_one"]);
+    assert!(mask.field_name().is_full());
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct First {
+    name: String,
+    apply: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Second {
+    name: String,
+    apply: u32,
+}
+
+#[test]
+fn same_shape() {
+    let first = FieldMask::<First>::try_from(FieldMaskInput(vec!["name"].into_iter()))
+        .expect("unable to deserialize mask");
+    let second = FieldMask::<Second>::try_from(FieldMaskInput(vec!["apply"].into_iter()))
+        .expect("unable to deserialize mask");
+
+    assert!(first.field_name().is_full());
+    assert!(first.field_apply().is_empty());
+    assert!(second.field_name().is_empty());
+    assert!(second.field_apply().is_full());
+    assert_eq!(
+        <FieldMask<Second> as SecondFieldMaskExt>::from_field_apply(FieldMask::<u32>::from_mask(
+            true
+        ))
+        .to_paths()
+        .unwrap(),
+        vec!["apply"],
+    );
+}
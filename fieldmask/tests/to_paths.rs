@@ -0,0 +1,76 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    primitive: String,
+    child_1: Child,
+    child_2: Child,
+    #[fieldmask(flatten)]
+    one_of_field: Option<OneOfField>,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Child {
+    field_one: String,
+    field_two: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum OneOfField {
+    VariantOne(String),
+    VariantTwo(u32),
+}
+
+impl Default for OneOfField {
+    fn default() -> Self {
+        Self::VariantOne(String::default())
+    }
+}
+
+#[test]
+fn to_paths() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["primitive", "child_1.field_two", "child_2", "variant_two"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["primitive", "child_1.field_two", "child_2", "variant_two"],
+    );
+}
+
+#[test]
+fn full_child_is_collapsed() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["child_1.field_one", "child_1.field_two"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+
+    assert_eq!(mask.to_paths().unwrap(), vec!["child_1"]);
+}
+
+#[test]
+fn empty_mask() {
+    assert!(FieldMask::<Parent>::default()
+        .to_paths()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn round_trip() {
+    let mask = FieldMask::<Parent>::try_from(FieldMaskInput(
+        vec!["child_2.field_one", "variant_one", "variant_two"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    let paths = mask.to_paths().unwrap();
+
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(paths.iter().map(String::as_str)))
+            .expect("unable to deserialize mask"),
+        mask,
+    );
+}
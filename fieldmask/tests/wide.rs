@@ -0,0 +1,122 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, Default, PartialEq, Maskable)]
+struct Wide {
+    f_00: u32,
+    f_01: u32,
+    f_02: u32,
+    f_03: u32,
+    f_04: u32,
+    f_05: u32,
+    f_06: u32,
+    f_07: u32,
+    f_08: u32,
+    f_09: u32,
+    f_10: u32,
+    f_11: u32,
+    f_12: u32,
+    f_13: u32,
+    f_14: u32,
+    f_15: u32,
+    f_16: u32,
+    f_17: u32,
+    f_18: u32,
+    f_19: u32,
+    f_20: u32,
+    f_21: u32,
+    f_22: u32,
+    f_23: u32,
+    f_24: u32,
+    f_25: u32,
+    f_26: u32,
+    f_27: u32,
+    f_28: u32,
+    f_29: Child,
+}
+
+#[derive(Debug, Default, PartialEq, Maskable)]
+struct Child {
+    a: u32,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+enum WideOneOf {
+    V00(u32),
+    V01(u32),
+    V02(u32),
+    V03(u32),
+    V04(u32),
+    V05(u32),
+    V06(u32),
+    V07(u32),
+    V08(u32),
+    V09(u32),
+    V10(u32),
+    V11(u32),
+    V12(u32),
+    V13(Child),
+}
+
+impl Default for WideOneOf {
+    fn default() -> Self {
+        Self::V00(0)
+    }
+}
+
+#[test]
+fn wide_struct() {
+    let mask = FieldMask::<Wide>::try_from(FieldMaskInput(
+        vec!["f_00", "f_11", "f_12", "f_23", "f_29.b"].into_iter(),
+    ))
+    .expect("unable to deserialize mask");
+    assert_eq!(
+        mask.to_paths().unwrap(),
+        vec!["f_00", "f_11", "f_12", "f_23", "f_29.b"]
+    );
+    assert!(mask.contains("f_29.b").unwrap());
+    assert!(!mask.contains("f_13").unwrap());
+
+    let mut target = Wide::default();
+    let src = Wide {
+        f_11: 1,
+        f_12: 2,
+        f_13: 3,
+        f_29: Child { a: 4, b: 5 },
+        ..Wide::default()
+    };
+    mask.apply(&mut target, src);
+    assert_eq!(
+        target,
+        Wide {
+            f_11: 1,
+            f_12: 2,
+            f_29: Child { a: 0, b: 5 },
+            ..Wide::default()
+        },
+    );
+
+    assert_eq!(
+        (!FieldMask::<Wide>::default()).to_paths().unwrap().len(),
+        30
+    );
+    assert_eq!(Wide::all_paths().len(), 32);
+}
+
+#[test]
+fn wide_one_of() {
+    let mut target = Some(WideOneOf::V00(1));
+    let mask = FieldMask::<Option<WideOneOf>>::try_from(FieldMaskInput(vec!["v13.a"].into_iter()))
+        .expect("unable to deserialize mask");
+    mask.apply(&mut target, Some(WideOneOf::V13(Child { a: 2, b: 3 })));
+    assert_eq!(target, Some(WideOneOf::V13(Child { a: 2, b: 0 })));
+
+    assert_eq!(
+        FieldMask::diff(&Some(WideOneOf::V12(1)), &Some(WideOneOf::V12(2)))
+            .to_paths()
+            .unwrap(),
+        vec!["v12"],
+    );
+}
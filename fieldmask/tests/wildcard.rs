@@ -0,0 +1,79 @@
+use std::convert::TryFrom;
+
+use fieldmask::{FieldMask, FieldMaskInput, Maskable};
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Child {
+    a: u32,
+    b: u32,
+}
+
+#[derive(Debug, PartialEq, Maskable)]
+struct Parent {
+    child: Child,
+    c: u32,
+}
+
+#[test]
+fn full_replacement() {
+    let mut struct1 = Parent {
+        child: Child { a: 1, b: 2 },
+        c: 3,
+    };
+    let struct2 = Parent {
+        child: Child { a: 4, b: 5 },
+        c: 6,
+    };
+
+    let expected_struct = Parent {
+        child: Child { a: 4, b: 5 },
+        c: 6,
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["*"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn nested_wildcard() {
+    let mut struct1 = Parent {
+        child: Child { a: 1, b: 2 },
+        c: 3,
+    };
+    let struct2 = Parent {
+        child: Child { a: 4, b: 5 },
+        c: 6,
+    };
+
+    let expected_struct = Parent {
+        child: Child { a: 4, b: 5 },
+        c: 3,
+    };
+
+    FieldMask::try_from(FieldMaskInput(vec!["child.*"].into_iter()))
+        .expect("unable to deserialize mask")
+        .apply(&mut struct1, struct2);
+    assert_eq!(struct1, expected_struct);
+}
+
+#[test]
+fn primitive_wildcard() {
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec!["c.*"].into_iter()))
+            .expect("unable to deserialize mask"),
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec!["c"].into_iter()))
+            .expect("unable to deserialize mask"),
+    );
+}
+
+#[test]
+fn wildcard_in_the_middle() {
+    assert_eq!(
+        FieldMask::<Parent>::try_from(FieldMaskInput(vec!["*.a"].into_iter()))
+            .expect_err("should fail to parse fieldmask")
+            .entry,
+        "*.a",
+    );
+}
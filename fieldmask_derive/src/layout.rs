@@ -0,0 +1,189 @@
+use proc_macro2::{Ident, Literal, TokenStream, TokenTree};
+use quote::quote;
+use syn::{GenericParam, Generics, Index, Type};
+
+use crate::utils::Field;
+
+/// The standard traits are only implemented for tuples of up to 12 elements, so the mask of a
+/// wider type is a tuple of tuples.
+const TUPLE_LEN: usize = 12;
+
+/// The number of fields that have a bit in a `LeafMask`.
+const WORD_LEN: usize = 64;
+
+/// Where the mask of a field is stored in the mask of its type.
+pub enum Slot {
+    /// A `FieldMask` of the field.
+    Field(TokenStream),
+    /// The storage that `PackedStorage` picks for the field, which is the `FieldMask` of the field
+    /// or nothing when it's a `bit` of a `LeafMask`.
+    Packed {
+        storage: TokenStream,
+        word: TokenStream,
+        bit: Literal,
+    },
+}
+
+impl Slot {
+    /// The `FieldMask` of a field of type `ty` in `mask`, as a place that can be borrowed.
+    pub fn read(&self, mask: &TokenStream, ty: &Type) -> TokenStream {
+        match self {
+            Slot::Field(access) => quote!(#mask#access),
+            Slot::Packed { .. } => {
+                let borrow = self.borrow(mask, ty);
+                quote!((*#borrow))
+            }
+        }
+    }
+
+    /// A reference to the `FieldMask` of a field of type `ty` in `mask`.
+    pub fn borrow(&self, mask: &TokenStream, ty: &Type) -> TokenStream {
+        match self {
+            Slot::Field(access) => quote!(&#mask#access),
+            Slot::Packed { storage, word, bit } => quote! {
+                ::fieldmask::PackedField::<#ty>::get(&#mask#storage, &#mask#word, #bit)
+            },
+        }
+    }
+
+    /// A `FieldMaskMut` of the `FieldMask` of a field of type `ty` in `mask`.
+    pub fn borrow_mut(&self, mask: &TokenStream, ty: &Type) -> TokenStream {
+        match self {
+            Slot::Field(access) => quote!(::fieldmask::FieldMaskMut::from(&mut #mask#access)),
+            Slot::Packed { storage, word, bit } => quote! {
+                ::fieldmask::PackedField::<#ty>::get_mut(&mut #mask#storage, &mut #mask#word, #bit)
+            },
+        }
+    }
+
+    /// The `FieldMask` of a field of type `ty`, moved out of `mask`.
+    pub fn take(&self, mask: &TokenStream, ty: &Type) -> TokenStream {
+        match self {
+            Slot::Field(access) => quote!(#mask#access),
+            Slot::Packed { storage, word, bit } => quote! {
+                ::fieldmask::PackedField::<#ty>::take(#mask#storage, &#mask#word, #bit)
+            },
+        }
+    }
+
+    /// Evaluate the expression built by `body` with a mutable `FieldMask` of a field of type `ty`
+    /// in `mask`, which is written back for a bit.
+    pub fn update(
+        &self,
+        mask: &TokenStream,
+        ty: &Type,
+        body: impl FnOnce(TokenStream) -> TokenStream,
+    ) -> TokenStream {
+        match self {
+            Slot::Field(access) => body(quote!(#mask#access)),
+            Slot::Packed { .. } => {
+                let borrow_mut = self.borrow_mut(mask, ty);
+                let body = body(quote!((*field)));
+                quote! {{
+                    let mut field = #borrow_mut;
+                    #body
+                }}
+            }
+        }
+    }
+}
+
+/// The `Mask` of a type with `fields`, and the slot of each field in it.
+///
+/// Every field of a packed type has a bit in the `LeafMask`s that follow the other masks, which is
+/// only used if it's a leaf according to `Maskable::LEAF`. The fields whose type mentions a
+/// parameter of `generics` keep their `FieldMask`, since a constant can't depend on it.
+pub fn layout(fields: &[Field], packed: bool, generics: &Generics) -> (TokenStream, Vec<Slot>) {
+    let params = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Type(param) => param.ident.clone(),
+            GenericParam::Lifetime(param) => param.lifetime.ident.clone(),
+            GenericParam::Const(param) => param.ident.clone(),
+        })
+        .collect::<Vec<_>>();
+    let is_packed = |field: &Field| {
+        let ty = &field.ty;
+        packed && !field.is_flatten && !mentions(quote!(#ty), &params)
+    };
+    let mut items = Vec::new();
+    let mut packed_tys = Vec::new();
+    for field in fields {
+        let ty = &field.ty;
+        if is_packed(field) {
+            items.push(quote! {
+                <::fieldmask::Packed<{ <#ty as ::fieldmask::Maskable>::LEAF }>
+                    as ::fieldmask::PackedStorage<#ty>>::Storage
+            });
+            packed_tys.push(ty);
+        } else {
+            items.push(quote!(::fieldmask::FieldMask<#ty>));
+        }
+    }
+    let first_word = items.len();
+    items.extend(packed_tys.chunks(WORD_LEN).map(|tys| {
+        let bits = (0..tys.len()).map(Literal::usize_unsuffixed);
+        quote! {
+            ::fieldmask::LeafMask<{
+                0 #(| (<#tys as ::fieldmask::Maskable>::LEAF as u64) << #bits)*
+            }>
+        }
+    }));
+
+    let len = items.len();
+    let mut bit = 0;
+    let slots = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            if is_packed(field) {
+                let slot = Slot::Packed {
+                    storage: access(index, len),
+                    word: access(first_word + bit / WORD_LEN, len),
+                    bit: Literal::usize_unsuffixed(bit % WORD_LEN),
+                };
+                bit += 1;
+                slot
+            } else {
+                Slot::Field(access(index, len))
+            }
+        })
+        .collect();
+    let mask_type = nest(
+        items,
+        |items| quote!(::fieldmask::BitwiseWrap<(#(#items,)*)>),
+    );
+    (mask_type, slots)
+}
+
+/// Whether `tokens` mention any of `idents`.
+pub fn mentions(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions(group.stream(), idents),
+        _ => false,
+    })
+}
+
+/// Nest `items` into tuples of at most `TUPLE_LEN` elements, each one built by `wrap`.
+fn nest(mut items: Vec<TokenStream>, wrap: impl Fn(&[TokenStream]) -> TokenStream) -> TokenStream {
+    while items.len() > TUPLE_LEN {
+        items = items.chunks(TUPLE_LEN).map(&wrap).collect();
+    }
+    wrap(&items)
+}
+
+/// The accessor of the `index`th of `len` items nested by `nest`, e.g. `.0.1.0.3`.
+fn access(mut index: usize, mut len: usize) -> TokenStream {
+    let mut positions = Vec::new();
+    while len > TUPLE_LEN {
+        positions.push(index % TUPLE_LEN);
+        index /= TUPLE_LEN;
+        len = len.div_ceil(TUPLE_LEN);
+    }
+    positions.push(index);
+    let wrapped = Index::from(0);
+    let positions = positions.into_iter().rev().map(Index::from);
+    quote!(#(.#wrapped.#positions)*)
+}
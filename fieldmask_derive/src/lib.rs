@@ -1,9 +1,10 @@
-use inflector::cases::snakecase::to_snake_case;
+use inflector::cases::{camelcase::to_camel_case, snakecase::to_snake_case};
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Index};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote};
 use utils::{Item, ItemInfo, ItemType};
 
+mod layout;
 mod utils;
 
 #[proc_macro_derive(Maskable, attributes(fieldmask))]
@@ -11,57 +12,254 @@ pub fn derive_maskable(input: TokenStream) -> TokenStream {
     let input: Item = parse_macro_input!(input);
     let ItemInfo {
         item_type,
+        vis,
         ident,
         generics,
         fields,
+        packed,
     } = input.get_info();
 
     let (impl_generics, ty_generics, where_clauses) = generics.split_for_impl();
-    let field_indices = fields.iter().enumerate().map(|(i, _field)| Index::from(i));
-    let field_idents = fields.iter().map(|field| &field.ident).collect::<Vec<_>>();
-    let field_types = fields.iter().map(|f| f.ty);
+    let (mask_type, slots) = layout::layout(&fields, packed, generics);
+    let mask = quote!(mask);
+    let changes = quote!(changes);
+    // The `FieldMask` of the `i`th field in `mask`, borrowed or moved out of it.
+    let field_mask = |i: usize| slots[i].read(&mask, &fields[i].ty);
+    let take_field_mask = |i: usize| slots[i].take(&mask, &fields[i].ty);
+    // Candidates for an unknown field are the fields of the type and the ones of its flattened
+    // fields, which are only listed by `append_field_names` when the error is reported.
+    let names = fields
+        .iter()
+        .filter(|field| !field.is_flatten)
+        .map(|field| &field.name);
+    let flattened_tys = fields
+        .iter()
+        .filter(|field| field.is_flatten)
+        .map(|field| &field.ty);
+    let unknown_field = quote! {
+        ::fieldmask::DeserializeMaskError::unknown_field(stringify!(#ident), field_mask_segs[0])
+            .with_candidates(<Self as ::fieldmask::Maskable>::append_field_names)
+    };
+    // A field can be written quoted or not.
+    let segment = |name: &str| {
+        quote! {
+            ::fieldmask::Segment::Unquoted(#name) | ::fieldmask::Segment::Quoted(#name)
+        }
+    };
     let match_arms = fields.iter().enumerate().map(|(i, field)| {
-        let index = Index::from(i);
+        let sub_mask = field_mask(i);
         if field.is_flatten {
             quote! {
-                _ if mask
-                    .0
-                    .#index
-                    .try_bitor_assign(field_mask_segs)
+                _ if #sub_mask
+                    .try_bitor_assign_segs(field_mask_segs)
                     .map(|_| true)
-                    .or_else(|l| if l.depth == 0 { Ok(false) } else { Err(l) })? =>
-                {
-                    ()
+                    .or_else(|l| if l.depth == 0 { Ok(false) } else { Err(l) })? => {}
+            }
+        } else {
+            let seg = segment(&field.name);
+            let update = slots[i].update(&mask, &field.ty, |field_mask| {
+                quote! {
+                    #field_mask.try_bitor_assign_segs(tail).map_err(|mut e| {
+                        e.depth += 1;
+                        e
+                    })?
                 }
+            });
+            quote! {
+                [#seg, tail @ ..] => #update,
+            }
+        }
+    });
+    let query_body = |method: &str, whole: proc_macro2::TokenStream| {
+        let method = format_ident!("{}", method);
+        let arms = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| !field.is_flatten)
+            .map(|(i, field)| {
+                let sub_mask = field_mask(i);
+                let seg = segment(&field.name);
+                quote! {
+                    [#seg, tail @ ..] => return #sub_mask.#method(tail).map_err(|mut e| {
+                        e.depth += 1;
+                        e
+                    }),
+                }
+            });
+        let flattened = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.is_flatten)
+            .map(|(i, _)| {
+                let sub_mask = field_mask(i);
+                quote! {
+                    match #sub_mask.#method(field_mask_segs) {
+                        ::core::result::Result::Err(e) if e.depth == 0 => {}
+                        result => return result,
+                    }
+                }
+            });
+        quote! {
+            match field_mask_segs {
+                [] | [::fieldmask::Segment::Unquoted("*")] => return ::core::result::Result::Ok(#whole),
+                #(#arms)*
+                _ => {}
+            }
+            #(#flattened)*
+            ::core::result::Result::Err(#unknown_field)
+        }
+    };
+    let contains_body = query_body("contains_segs", quote!(*mask == !Self::Mask::default()));
+    let intersects_body = query_body("intersects_segs", quote!(*mask != Self::Mask::default()));
+    let path_stmts = fields.iter().enumerate().map(|(i, field)| {
+        let sub_mask = field_mask(i);
+        if field.is_flatten {
+            quote! {
+                #sub_mask.append_paths(prefix, paths)?;
             }
         } else {
-            let prefix = to_snake_case(&field.ident.to_string());
+            let name = &field.name;
             quote! {
-                [#prefix, tail @ ..] => mask.0.#index.try_bitor_assign(tail).map_err(|mut e| {
-                    e.depth += 1;
-                    e
-                })?,
+                let path = if prefix.is_empty() {
+                    ::std::string::String::from(#name)
+                } else {
+                    ::std::format!("{}.{}", prefix, #name)
+                };
+                if #sub_mask == !::fieldmask::FieldMask::default() {
+                    paths.push(path);
+                } else {
+                    #sub_mask.append_paths(&path, paths)?;
+                }
+            }
+        }
+    });
+    let all_path_stmts = fields.iter().map(|field| {
+        let ty = &field.ty;
+        if field.is_flatten {
+            quote! {
+                <#ty as ::fieldmask::Maskable>::append_all_paths(prefix, paths);
+            }
+        } else {
+            let name = &field.name;
+            quote! {
+                let path = if prefix.is_empty() {
+                    ::std::string::String::from(#name)
+                } else {
+                    ::std::format!("{}.{}", prefix, #name)
+                };
+                paths.push(::fieldmask::PathInfo {
+                    path: ::core::clone::Clone::clone(&path),
+                    type_str: <#ty as ::fieldmask::Maskable>::TYPE_STR,
+                });
+                <#ty as ::fieldmask::Maskable>::append_all_paths(&path, paths);
+            }
+        }
+    });
+    // Flattened fields are tried after the other ones, since their names are not known here.
+    let json_arms = fields
+        .iter()
+        .filter(|field| !field.is_flatten)
+        .map(|field| {
+            let ty = &field.ty;
+            let name = &field.name;
+            let json_name = to_camel_case(name);
+            let (name_seg, json_seg) = (segment(name), segment(&json_name));
+            quote! {
+                [#name_seg, tail @ ..] if to_json => {
+                    renamed.push(::fieldmask::Segment::Unquoted(#json_name));
+                    <#ty as ::fieldmask::Maskable>::append_json_segs(tail, to_json, renamed);
+                    return true;
+                }
+                [#json_seg, tail @ ..] if !to_json => {
+                    renamed.push(::fieldmask::Segment::Unquoted(#name));
+                    <#ty as ::fieldmask::Maskable>::append_json_segs(tail, to_json, renamed);
+                    return true;
+                }
+            }
+        });
+    let json_flattened_stmts = fields.iter().filter(|field| field.is_flatten).map(|field| {
+        let ty = &field.ty;
+        quote! {
+            let len = renamed.len();
+            if <#ty as ::fieldmask::Maskable>::append_json_segs(field_mask_segs, to_json, renamed) {
+                return true;
             }
+            renamed.truncate(len);
         }
     });
-    let match_arm_groups = fields.iter().map(|target_field| {
+    let match_arm_groups = |method: &str| {
+        let by_value = method == "apply";
+        let method = format_ident!("{}", method);
+        fields
+            .iter()
+            .map(|target_field| {
+                let target_ident = target_field.ident;
+                let arms = fields.iter().enumerate().map(|(i, src_field)| {
+                    let sub_mask = field_mask(i);
+                    let op_mask = if by_value {
+                        take_field_mask(i)
+                    } else {
+                        field_mask(i)
+                    };
+                    let src_ident = src_field.ident;
+                    if src_ident == target_ident {
+                        quote! {
+                            Self::#src_ident(s) if #sub_mask != ::fieldmask::FieldMask::default() => {
+                                #op_mask.#method(t, s);
+                            }
+                        }
+                    } else {
+                        let src_ty = &src_field.ty;
+                        quote! {
+                            Self::#src_ident(s) if #sub_mask != ::fieldmask::FieldMask::default() => {
+                                let mut new = <#src_ty>::default();
+                                #op_mask.#method(&mut new, s);
+                                *self = Self::#src_ident(new);
+                            }
+                        }
+                    }
+                });
+                quote! {
+                    Self::#target_ident(t) => match src {
+                        #(#arms)*
+                        _ => return false,
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+    let apply_arm_groups = match_arm_groups("apply");
+    let apply_ref_arm_groups = match_arm_groups("apply_ref");
+    let changes_arm_groups = fields.iter().map(|target_field| {
         let target_ident = target_field.ident;
         let arms = fields.iter().enumerate().map(|(i, src_field)| {
-            let index = Index::from(i);
+            let sub_mask = field_mask(i);
+            let taken_mask = take_field_mask(i);
             let src_ident = src_field.ident;
             if src_ident == target_ident {
+                let update = slots[i].update(
+                    &changes,
+                    &src_field.ty,
+                    |changes| quote!(#changes = #taken_mask.apply_with_changes(t, s)),
+                );
                 quote! {
-                    Self::#src_ident(s) if mask.0.#index != ::fieldmask::FieldMask::default() => {
-                        mask.0.#index.apply(t, s);
+                    Self::#src_ident(s) if #sub_mask != ::fieldmask::FieldMask::default() => {
+                        #update;
                     }
                 }
             } else {
-                let src_ty = src_field.ty;
+                let src_ty = &src_field.ty;
+                let update = slots[i].update(
+                    &changes,
+                    src_ty,
+                    |changes| quote!(#changes = !::fieldmask::FieldMask::default()),
+                );
                 quote! {
-                    Self::#src_ident(s) if mask.0.#index != ::fieldmask::FieldMask::default() => {
+                    Self::#src_ident(s) if #sub_mask != ::fieldmask::FieldMask::default() => {
                         let mut new = <#src_ty>::default();
-                        mask.0.#index.apply(&mut new, s);
+                        #taken_mask.apply(&mut new, s);
                         *self = Self::#src_ident(new);
+                        #update;
                     }
                 }
             }
@@ -69,11 +267,384 @@ pub fn derive_maskable(input: TokenStream) -> TokenStream {
         quote! {
             Self::#target_ident(t) => match src {
                 #(#arms)*
-                _ => return false,
+                _ => return (false, changes),
             }
         }
     });
 
+    let diff_arms = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident;
+        let diff = slots[i].update(
+            &mask,
+            &field.ty,
+            |field_mask| quote!(#field_mask = ::fieldmask::FieldMask::diff(s, o)),
+        );
+        let full = slots[i].update(
+            &mask,
+            &field.ty,
+            |field_mask| quote!(#field_mask = !::fieldmask::FieldMask::default()),
+        );
+        quote! {
+            (Self::#ident(s), Self::#ident(o)) => #diff,
+            (_, Self::#ident(_)) => #full,
+        }
+    });
+    let project_arms = fields.iter().enumerate().map(|(i, field)| {
+        let sub_mask = field_mask(i);
+        let ident = field.ident;
+        quote! {
+            Self::#ident(t) if #sub_mask != ::fieldmask::FieldMask::default() => {
+                #sub_mask.project_in_place(t);
+            }
+        }
+    });
+
+    let getters = fields
+        .iter()
+        .map(|field| match item_type {
+            ItemType::Struct => field.ident.clone(),
+            ItemType::Enum => format_ident!("{}", to_snake_case(&field.ident.to_string())),
+        })
+        .collect::<Vec<_>>();
+    let ext_ident = format_ident!("{}FieldMaskExt", ident);
+    // The accessors are prefixed with `field_`, so that they can't be named like a method of
+    // `FieldMask`, which would shadow them or make calls ambiguous.
+    let accessor_names = getters
+        .iter()
+        .map(|getter| format_ident!("field_{}", getter))
+        .collect::<Vec<_>>();
+    let accessors =
+        fields
+            .iter()
+            .zip(&accessor_names)
+            .zip(&slots)
+            .map(|((field, getter), slot)| {
+                let ty = &field.ty;
+                let lift_ty = &field.lift_ty;
+                let sub_mask = slot.borrow(&mask, ty);
+                let sub_mask_mut = slot.borrow_mut(&mask, ty);
+                let getter_mut = format_ident!("{}_mut", getter);
+                let lift = format_ident!("from_{}", getter);
+                let getter_doc = format!("The mask of `{}`.", field.name);
+                let getter_mut_doc = format!("The mutable mask of `{}`.", field.name);
+                let lift_doc = format!(
+                    "Build a mask that only includes `mask` under `{}`.",
+                    field.name
+                );
+                let getter_decl = quote! {
+                    #[doc = #getter_doc]
+                    fn #getter<'__a>(&'__a self) -> &'__a ::fieldmask::FieldMask<#ty>
+                    where
+                        #ident#ty_generics: '__a
+                };
+                let getter_mut_decl = quote! {
+                    #[doc = #getter_mut_doc]
+                    fn #getter_mut<'__a>(&'__a mut self) -> ::fieldmask::FieldMaskMut<'__a, #ty>
+                    where
+                        #ident#ty_generics: '__a
+                };
+                let lift_decl = quote! {
+                    #[doc = #lift_doc]
+                    fn #lift(mask: ::fieldmask::FieldMask<#lift_ty>) -> Self
+                };
+                let decl = quote! {
+                    #getter_decl;
+                    #getter_mut_decl;
+                    #lift_decl;
+                };
+                let imp = quote! {
+                    #getter_decl,
+                    {
+                        let mask: &<#ident#ty_generics as ::fieldmask::Maskable>::Mask =
+                            ::core::convert::AsRef::as_ref(self);
+                        #sub_mask
+                    }
+
+                    #getter_mut_decl,
+                    {
+                        let mask: &mut <#ident#ty_generics as ::fieldmask::Maskable>::Mask =
+                            ::core::convert::AsMut::as_mut(self);
+                        #sub_mask_mut
+                    }
+
+                    #lift_decl
+                    {
+                        let mut parent = Self::default();
+                        *<Self as #ext_ident#ty_generics>::#getter_mut(&mut parent) =
+                            ::fieldmask::FieldMask::from_mask(mask.into_mask());
+                        parent
+                    }
+                };
+                (decl, imp)
+            });
+    let (accessor_decls, accessor_impls): (Vec<_>, Vec<_>) = accessors.unzip();
+    let ext_doc = format!(
+        "Typed access to the masks of the fields of `{}`, which are also the ones of `Option<{}>`.",
+        ident, ident
+    );
+    let mut ext_option_generics = generics.clone();
+    // The bound is higher-ranked so that it is not rejected when it doesn't hold, i.e. when the
+    // type doesn't implement `Default`.
+    ext_option_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! {
+            for<'__a> ::core::option::Option<#ident#ty_generics>: ::fieldmask::Maskable<
+                Mask = <#ident#ty_generics as ::fieldmask::Maskable>::Mask,
+            >
+        });
+    let (_, _, ext_option_where_clause) = ext_option_generics.split_for_impl();
+
+    let paths_ident = format_ident!("{}Paths", ident);
+    let paths_doc = format!("Typed paths to the fields of `{}`.", ident);
+    let paths_fn_doc = format!(
+        "Typed paths to the fields of `{}`, which can be turned into a `FieldMask`.",
+        ident
+    );
+    let field_lift = |ty| quote!(::fieldmask::FieldLift<__L, #ident#ty_generics, #ty>);
+    let mut paths_generics = generics.clone();
+    paths_generics
+        .params
+        .push(parse_quote!(__R: ::fieldmask::Maskable));
+    paths_generics
+        .params
+        .push(parse_quote!(__L: ::fieldmask::Lift<#ident#ty_generics, __R>));
+    let mut paths_from_generics = paths_generics.clone();
+    paths_from_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! {
+            ::fieldmask::FieldMask<#ident#ty_generics>: ::core::default::Default
+                + ::core::ops::Not<Output = ::fieldmask::FieldMask<#ident#ty_generics>>
+        });
+    let (paths_impl_generics, paths_ty_generics, paths_where_clause) =
+        paths_generics.split_for_impl();
+    let (paths_from_impl_generics, _, paths_from_where_clause) =
+        paths_from_generics.split_for_impl();
+    let new_field_lift = |ty, accessor: &syn::Ident| {
+        let accessor_mut = format_ident!("{}_mut", accessor);
+        quote! {
+            ::fieldmask::FieldLift::new(lift, |mask: ::fieldmask::FieldMask<#ty>| {
+                let mut parent = ::fieldmask::FieldMask::<#ident#ty_generics>::default();
+                *<::fieldmask::FieldMask<#ident#ty_generics> as #ext_ident#ty_generics>::#accessor_mut(
+                    &mut parent,
+                ) = mask;
+                parent
+            })
+        }
+    };
+    // The flattened fields have no path of their own, and their fields are reached through the
+    // `FlattenedPaths` traits of their types instead.
+    let path_methods = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !field.is_flatten)
+        .map(|(i, field)| {
+            let getter = &getters[i];
+            let ty = &field.ty;
+            let lift = field_lift(ty);
+            let new_lift = new_field_lift(ty, &accessor_names[i]);
+            let doc = format!("The path to `{}`.", field.name);
+            let signature = quote! {
+                fn #getter(self) -> <#ty as ::fieldmask::MaskablePaths<__R, #lift>>::Paths
+                where
+                    #ty: ::fieldmask::MaskablePaths<__R, #lift>
+            };
+            let method = quote! {
+                #[doc = #doc]
+                #vis #signature
+                {
+                    let lift = self.lift;
+                    <#ty as ::fieldmask::MaskablePaths<__R, #lift>>::paths(#new_lift)
+                }
+            };
+            let flattened_method = quote! {
+                #[doc = #doc]
+                #signature
+                {
+                    ::fieldmask::Flattened::<__F>::flattened(self).#getter()
+                }
+            };
+            (method, flattened_method)
+        })
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+    let (path_methods, flattened_path_methods) = path_methods;
+    let flattened_impls = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.is_flatten)
+        .map(|(i, field)| {
+            let ty = &field.ty;
+            let lift = field_lift(ty);
+            let new_lift = new_field_lift(ty, &accessor_names[i]);
+            let paths_ty = quote!(<#ty as ::fieldmask::MaskablePaths<__R, #lift>>::Paths);
+            let mut generics = paths_generics.clone();
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote!(#ty: ::fieldmask::MaskablePaths<__R, #lift>));
+            let (_, _, where_clause) = generics.split_for_impl();
+            quote! {
+                impl#paths_impl_generics
+                    ::fieldmask::Flattened<#ty>
+                    for #paths_ident#paths_ty_generics
+                #where_clause
+                {
+                    type Paths = #paths_ty;
+
+                    fn flattened(self) -> #paths_ty {
+                        let lift = self.lift;
+                        <#ty as ::fieldmask::MaskablePaths<__R, #lift>>::paths(#new_lift)
+                    }
+                }
+            }
+        });
+    let flattened_paths_ident = format_ident!("{}FlattenedPaths", ident);
+    let flattened_paths_doc = format!(
+        "Typed paths to the fields of `{}` from the typed paths of a type that flattens it.",
+        ident
+    );
+    let mut flattened_paths_generics = paths_generics.clone();
+    flattened_paths_generics.params.push(parse_quote!(__F));
+    let (flattened_paths_decl_generics, _, _) = flattened_paths_generics.split_for_impl();
+    let paths_generic_args = paths_generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            syn::GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+        })
+        .collect::<Vec<_>>();
+    // The trait is implemented for the typed paths that flatten a field of the type, and for the
+    // ones that flatten an `Option` of it.
+    let flattened_paths_impls = [
+        quote!(#ident#ty_generics),
+        quote!(::core::option::Option<#ident#ty_generics>),
+    ]
+    .iter()
+    .map(|flattened_ty| {
+        let mut generics = paths_generics.clone();
+        generics.params.push(parse_quote! {
+            __P: ::fieldmask::Flattened<#flattened_ty, Paths = #paths_ident#paths_ty_generics>
+        });
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        quote! {
+            impl#impl_generics #flattened_paths_ident<#(#paths_generic_args,)* #flattened_ty> for __P
+            #where_clause
+            {
+            }
+        }
+    })
+    .collect::<Vec<_>>();
+
+    // Call the operation `op` of the mask of the `i`th field, which `Elements` provides for the
+    // elements of a vector, since their mask is not the mask of a `SelfMaskable` type.
+    let field_op =
+        |i: usize, sub_mask: proc_macro2::TokenStream, op: &str, args: proc_macro2::TokenStream| {
+            let op = format_ident!("{}", op);
+            if fields[i].is_elements {
+                let by_ref = if op == "apply_ref" || op == "project_in_place" {
+                    quote!(&)
+                } else {
+                    quote!()
+                };
+                quote!(::fieldmask::Elements::#op(#by_ref#sub_mask, #args))
+            } else {
+                quote!(#sub_mask.#op(#args))
+            }
+        };
+    let apply_stmts = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident;
+        field_op(
+            i,
+            take_field_mask(i),
+            "apply",
+            quote!(&mut self.#ident, src.#ident),
+        )
+    });
+    let apply_ref_stmts = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident;
+        field_op(
+            i,
+            field_mask(i),
+            "apply_ref",
+            quote!(&mut self.#ident, &src.#ident),
+        )
+    });
+    let changes_stmts = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident;
+        slots[i].update(&changes, &field.ty, |changes| {
+            let apply = field_op(
+                i,
+                take_field_mask(i),
+                "apply_with_changes",
+                quote!(&mut self.#ident, src.#ident),
+            );
+            quote!(#changes = #apply)
+        })
+    });
+    let project_stmts = fields.iter().enumerate().map(|(i, field)| {
+        let ident = field.ident;
+        field_op(
+            i,
+            field_mask(i),
+            "project_in_place",
+            quote!(&mut self.#ident),
+        )
+    });
+    let diff_stmts = fields.iter().zip(&slots).map(|(field, slot)| {
+        let ident = field.ident;
+        // The mask of the elements of a vector is not the mask of a `SelfMaskable` type.
+        let diff = if field.is_elements {
+            quote!(::fieldmask::Elements::diff)
+        } else {
+            quote!(::fieldmask::FieldMask::diff)
+        };
+        slot.update(
+            &mask,
+            &field.ty,
+            |sub_mask| quote!(#sub_mask = #diff(&self.#ident, &other.#ident)),
+        )
+    });
+
+    // The where clause of an implementation of `op_trait`, which requires it for every field. The
+    // bounds are higher-ranked, so that they are checked where the implementation is used rather
+    // than where it's derived, and a type whose fields don't all implement it can still be masked.
+    // The fields behind a pointer, which may be the type itself, are left out, since requiring
+    // them would make the bounds of a recursive type depend on themselves.
+    let pointers = [
+        format_ident!("Box"),
+        format_ident!("Rc"),
+        format_ident!("Arc"),
+    ];
+    let op_where_clause = |op_trait: proc_macro2::TokenStream| {
+        let mut generics = generics.clone();
+        let where_clause = generics.make_where_clause();
+        for field in &fields {
+            let op_ty = &field.op_ty;
+            if layout::mentions(quote!(#op_ty), &pointers) {
+                continue;
+            }
+            where_clause
+                .predicates
+                .push(parse_quote!(for<'__fieldmask> #op_ty: #op_trait));
+        }
+        quote!(#where_clause)
+    };
+    let ref_where_clause = op_where_clause(quote!(::fieldmask::SelfMaskableRef));
+    let diff_where_clause = op_where_clause(quote!(::fieldmask::DiffMaskable));
+    let changes_where_clause = op_where_clause(quote!(::fieldmask::SelfMaskableChanges));
+
     let additional_impl = match item_type {
         ItemType::Enum => quote! {
             impl#impl_generics ::fieldmask::OptionMaskable for #ident#ty_generics
@@ -81,10 +652,57 @@ pub fn derive_maskable(input: TokenStream) -> TokenStream {
             {
                 fn apply_mask(&mut self, src: Self, mask: Self::Mask) -> bool {
                     match self {
-                        #(#match_arm_groups)*
+                        #(#apply_arm_groups)*
                     }
                     return true;
                 }
+
+                fn project_mask(&mut self, mask: &Self::Mask) -> bool {
+                    match self {
+                        #(#project_arms)*
+                        _ => return false,
+                    }
+                    true
+                }
+            }
+
+            impl#impl_generics ::fieldmask::OptionMaskableRef for #ident#ty_generics
+            #ref_where_clause
+            {
+                fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) -> bool {
+                    match self {
+                        #(#apply_ref_arm_groups)*
+                    }
+                    return true;
+                }
+            }
+
+            impl#impl_generics ::fieldmask::OptionMaskableChanges for #ident#ty_generics
+            #changes_where_clause
+            {
+                fn apply_mask_with_changes(
+                    &mut self,
+                    src: Self,
+                    mask: Self::Mask,
+                ) -> (bool, Self::Mask) {
+                    let mut changes = Self::Mask::default();
+                    match self {
+                        #(#changes_arm_groups)*
+                    }
+                    (true, changes)
+                }
+            }
+
+            impl#impl_generics ::fieldmask::DiffMaskable for #ident#ty_generics
+            #diff_where_clause
+            {
+                fn diff_mask(&self, other: &Self) -> Self::Mask {
+                    let mut mask = Self::Mask::default();
+                    match (self, other) {
+                        #(#diff_arms)*
+                    }
+                    mask
+                }
             }
         },
         ItemType::Struct => quote! {
@@ -92,7 +710,39 @@ pub fn derive_maskable(input: TokenStream) -> TokenStream {
             #where_clauses
             {
                 fn apply_mask(&mut self, src: Self, mask: Self::Mask) {
-                    #(mask.0.#field_indices.apply(&mut self.#field_idents, src.#field_idents);)*
+                    #(#apply_stmts;)*
+                }
+
+                fn project_mask(&mut self, mask: &Self::Mask) {
+                    #(#project_stmts;)*
+                }
+            }
+
+            impl#impl_generics ::fieldmask::SelfMaskableRef for #ident#ty_generics
+            #ref_where_clause
+            {
+                fn apply_mask_ref(&mut self, src: &Self, mask: &Self::Mask) {
+                    #(#apply_ref_stmts;)*
+                }
+            }
+
+            impl#impl_generics ::fieldmask::SelfMaskableChanges for #ident#ty_generics
+            #changes_where_clause
+            {
+                fn apply_mask_with_changes(&mut self, src: Self, mask: Self::Mask) -> Self::Mask {
+                    let mut changes = Self::Mask::default();
+                    #(#changes_stmts;)*
+                    changes
+                }
+            }
+
+            impl#impl_generics ::fieldmask::DiffMaskable for #ident#ty_generics
+            #diff_where_clause
+            {
+                fn diff_mask(&self, other: &Self) -> Self::Mask {
+                    let mut mask = Self::Mask::default();
+                    #(#diff_stmts;)*
+                    mask
                 }
             }
         },
@@ -102,26 +752,161 @@ pub fn derive_maskable(input: TokenStream) -> TokenStream {
         impl#impl_generics ::fieldmask::Maskable for #ident#ty_generics
         #where_clauses
         {
-            type Mask = ::fieldmask::BitwiseWrap<(#(::fieldmask::FieldMask<#field_types>,)*)>;
+            type Mask = #mask_type;
+
+            const TYPE_STR: &'static ::core::primitive::str = stringify!(#ident);
 
             fn try_bitor_assign_mask(
                 mask: &mut Self::Mask,
-                field_mask_segs: &[&::core::primitive::str],
+                field_mask_segs: &[::fieldmask::Segment<&::core::primitive::str>],
             ) -> ::core::result::Result<(), ::fieldmask::DeserializeMaskError> {
-                match field_mask_segs {
-                    [] => *mask = !Self::Mask::default(),
+                    match field_mask_segs {
+                    [] | [::fieldmask::Segment::Unquoted("*")] => *mask = !Self::Mask::default(),
                     #(#match_arms)*
-                    _ => return ::core::result::Result::Err(::fieldmask::DeserializeMaskError{
-                        type_str: stringify!(#ident),
-                        field: field_mask_segs[0].into(),
-                        depth: 0,
-                    }),
+                    _ => return ::core::result::Result::Err(#unknown_field),
                 }
                 Ok(())
             }
+
+            fn append_mask_paths(
+                mask: &Self::Mask,
+                prefix: &::core::primitive::str,
+                paths: &mut ::std::vec::Vec<::std::string::String>,
+            ) -> ::core::result::Result<(), ::fieldmask::ToPathsError> {
+                #({ #path_stmts })*
+                ::core::result::Result::Ok(())
+            }
+
+            fn mask_contains(
+                mask: &Self::Mask,
+                field_mask_segs: &[::fieldmask::Segment<&::core::primitive::str>],
+            ) -> ::core::result::Result<bool, ::fieldmask::DeserializeMaskError> {
+                #contains_body
+            }
+
+            fn mask_intersects(
+                mask: &Self::Mask,
+                field_mask_segs: &[::fieldmask::Segment<&::core::primitive::str>],
+            ) -> ::core::result::Result<bool, ::fieldmask::DeserializeMaskError> {
+                #intersects_body
+            }
+
+            fn append_all_paths(
+                prefix: &::core::primitive::str,
+                paths: &mut ::std::vec::Vec<::fieldmask::PathInfo>,
+            ) {
+                #({ #all_path_stmts })*
+            }
+
+            fn append_field_names(names: &mut ::std::vec::Vec<&'static ::core::primitive::str>) {
+                names.extend_from_slice(&[#(#names),*]);
+                #(<#flattened_tys as ::fieldmask::Maskable>::append_field_names(names);)*
+            }
+
+            fn append_json_segs<'__a>(
+                field_mask_segs: &[::fieldmask::Segment<&'__a ::core::primitive::str>],
+                to_json: bool,
+                renamed: &mut ::std::vec::Vec<::fieldmask::Segment<&'__a ::core::primitive::str>>,
+            ) -> bool {
+                match field_mask_segs {
+                    #(#json_arms)*
+                    _ => {}
+                }
+                #({ #json_flattened_stmts })*
+                renamed.extend_from_slice(field_mask_segs);
+                ::core::matches!(field_mask_segs, [] | [::fieldmask::Segment::Unquoted("*"), ..])
+            }
         }
 
         #additional_impl
+
+        #[doc = #ext_doc]
+        #vis trait #ext_ident#impl_generics #where_clauses {
+            #(#accessor_decls)*
+        }
+
+        impl#impl_generics #ext_ident#ty_generics for ::fieldmask::FieldMask<#ident#ty_generics>
+        #where_clauses
+        {
+            #(#accessor_impls)*
+        }
+
+        impl#impl_generics #ext_ident#ty_generics
+            for ::fieldmask::FieldMask<::core::option::Option<#ident#ty_generics>>
+        #ext_option_where_clause
+        {
+            #(#accessor_impls)*
+        }
+
+        #[doc = #paths_doc]
+        #vis struct #paths_ident#paths_impl_generics #paths_where_clause {
+            lift: __L,
+            _marker: ::core::marker::PhantomData<fn() -> (__R, #ident#ty_generics)>,
+        }
+
+        impl#paths_impl_generics ::core::clone::Clone for #paths_ident#paths_ty_generics
+        #paths_where_clause
+        {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl#paths_impl_generics ::core::marker::Copy for #paths_ident#paths_ty_generics
+        #paths_where_clause
+        {
+        }
+
+        impl#paths_impl_generics #paths_ident#paths_ty_generics #paths_where_clause {
+            #(#path_methods)*
+        }
+
+        #(#flattened_impls)*
+
+        #[doc = #flattened_paths_doc]
+        #vis trait #flattened_paths_ident#flattened_paths_decl_generics:
+            ::fieldmask::Flattened<__F, Paths = #paths_ident#paths_ty_generics>
+            + ::core::marker::Sized
+        #paths_where_clause
+        {
+            #(#flattened_path_methods)*
+        }
+
+        #(#flattened_paths_impls)*
+
+        impl#paths_impl_generics ::fieldmask::MaskablePaths<__R, __L> for #ident#ty_generics
+        #paths_where_clause
+        {
+            type Paths = #paths_ident#paths_ty_generics;
+
+            fn paths(lift: __L) -> Self::Paths {
+                #paths_ident {
+                    lift,
+                    _marker: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl#paths_from_impl_generics ::core::convert::From<#paths_ident#paths_ty_generics>
+            for ::fieldmask::FieldMask<__R>
+        #paths_from_where_clause
+        {
+            fn from(paths: #paths_ident#paths_ty_generics) -> Self {
+                ::fieldmask::Lift::lift(paths.lift, !::fieldmask::FieldMask::default())
+            }
+        }
+
+        impl#impl_generics #ident#ty_generics #where_clauses {
+            #[doc = #paths_fn_doc]
+            #vis fn paths() -> <Self as ::fieldmask::MaskablePaths<Self, ::fieldmask::RootLift>>::Paths
+            where
+                Self: ::fieldmask::MaskablePaths<Self, ::fieldmask::RootLift>,
+            {
+                <Self as ::fieldmask::MaskablePaths<Self, ::fieldmask::RootLift>>::paths(
+                    ::fieldmask::RootLift,
+                )
+            }
+        }
     })
     .into()
 }
@@ -1,14 +1,19 @@
+use std::collections::HashSet;
+
+use inflector::cases::{
+    camelcase::to_camel_case, kebabcase::to_kebab_case,
+    screamingsnakecase::to_screaming_snake_case, snakecase::to_snake_case,
+};
 use syn::{
     braced, parenthesized,
     parse::{Parse, ParseStream},
+    parse_quote,
     punctuated::Punctuated,
     token::{Brace, Paren},
-    Attribute, Generics, Ident, Meta, NestedMeta, Token, Type, Visibility,
+    Attribute, GenericArgument, Generics, Ident, Lit, Meta, NestedMeta, PathArguments, Token, Type,
+    TypePath, Visibility,
 };
 
-#[cfg(feature = "prost")]
-use syn::Lit;
-
 struct Wrap<T>(pub T);
 
 impl<T: Parse> Parse for Wrap<Punctuated<T, Token![,]>> {
@@ -17,6 +22,18 @@ impl<T: Parse> Parse for Wrap<Punctuated<T, Token![,]>> {
     }
 }
 
+/// Parse the arguments of every `#[name(...)]` attribute in `attrs`.
+fn parse_attrs<T: Parse>(attrs: &[Attribute], name: &str) -> syn::Result<Vec<T>> {
+    Ok(attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(name))
+        .map(|attr| attr.parse_args())
+        .collect::<syn::Result<Vec<Wrap<Punctuated<T, Token![,]>>>>>()?
+        .into_iter()
+        .flat_map(|attrs| attrs.0)
+        .collect())
+}
+
 pub enum Item {
     Struct(ItemStruct),
     Enum(ItemEnum),
@@ -27,6 +44,7 @@ pub enum ItemType {
     Enum,
 }
 
+#[allow(dead_code)]
 pub struct ItemStruct {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
@@ -35,8 +53,11 @@ pub struct ItemStruct {
     pub generics: Generics,
     pub brace_token: Brace,
     pub fields: Punctuated<NamedField, Token![,]>,
+    pub rename_all: RenameRule,
+    pub packed: bool,
 }
 
+#[allow(dead_code)]
 pub struct ItemEnum {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
@@ -45,8 +66,11 @@ pub struct ItemEnum {
     pub generics: Generics,
     pub brace_token: Brace,
     pub variants: Punctuated<SingleTupleVariant, Token![,]>,
+    pub rename_all: RenameRule,
+    pub packed: bool,
 }
 
+#[allow(dead_code)]
 pub struct NamedField {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
@@ -54,26 +78,42 @@ pub struct NamedField {
     pub colon_token: Token![:],
     pub ty: Type,
     pub is_flatten: bool,
+    pub is_elements: bool,
+    pub rename: Option<String>,
 }
 
+#[allow(dead_code)]
 pub struct SingleTupleVariant {
     pub attrs: Vec<Attribute>,
     pub ident: Ident,
     pub paren_token: Paren,
     pub tuple_attrs: Vec<Attribute>,
     pub ty: Type,
+    pub rename: Option<String>,
 }
 
 impl Parse for Item {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let attrs = input.call(Attribute::parse_outer)?;
+        let container_attrs = parse_attrs(&attrs, "fieldmask")?;
+        let packed = container_attrs
+            .iter()
+            .any(|meta| matches!(meta, ContainerAttribute::Packed));
+        let rename_all = container_attrs
+            .into_iter()
+            .filter_map(|meta| match meta {
+                ContainerAttribute::RenameAll(rule) => Some(rule),
+                _ => None,
+            })
+            .next_back()
+            .unwrap_or(RenameRule::Snake);
 
         let vis = input.parse()?;
 
         let lookahead = input.lookahead1();
-        if lookahead.peek(Token![struct]) {
+        let item = if lookahead.peek(Token![struct]) {
             let content;
-            Ok(Self::Struct(ItemStruct {
+            Self::Struct(ItemStruct {
                 attrs,
                 vis,
                 struct_token: input.parse()?,
@@ -85,10 +125,12 @@ impl Parse for Item {
                 },
                 brace_token: braced!(content in input),
                 fields: content.parse_terminated(NamedField::parse)?,
-            }))
+                rename_all,
+                packed,
+            })
         } else if lookahead.peek(Token![enum]) {
             let content;
-            Ok(Self::Enum(ItemEnum {
+            Self::Enum(ItemEnum {
                 attrs,
                 vis,
                 enum_token: input.parse()?,
@@ -100,16 +142,70 @@ impl Parse for Item {
                 },
                 brace_token: braced!(content in input),
                 variants: content.parse_terminated(SingleTupleVariant::parse)?,
-            }))
+                rename_all,
+                packed,
+            })
         } else {
-            Err(lookahead.error())
+            return Err(lookahead.error());
+        };
+        item.check_names()?;
+        Ok(item)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum RenameRule {
+    Camel,
+    Snake,
+    Kebab,
+    ScreamingSnake,
+}
+
+impl RenameRule {
+    pub fn apply(self, ident: &Ident) -> String {
+        let ident = ident.to_string();
+        match self {
+            Self::Camel => to_camel_case(&ident),
+            Self::Snake => to_snake_case(&ident),
+            Self::Kebab => to_kebab_case(&ident),
+            Self::ScreamingSnake => to_screaming_snake_case(&ident),
+        }
+    }
+}
+
+enum ContainerAttribute {
+    RenameAll(RenameRule),
+    Packed,
+}
+
+impl Parse for ContainerAttribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let meta: NestedMeta = input.parse()?;
+        match meta {
+            NestedMeta::Meta(Meta::Path(p)) if p.is_ident("packed") => Ok(Self::Packed),
+            NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("rename_all") => match &m.lit {
+                Lit::Str(s) => match s.value().as_str() {
+                    "camelCase" => Ok(Self::RenameAll(RenameRule::Camel)),
+                    "snake_case" => Ok(Self::RenameAll(RenameRule::Snake)),
+                    "kebab-case" => Ok(Self::RenameAll(RenameRule::Kebab)),
+                    "SCREAMING_SNAKE_CASE" => Ok(Self::RenameAll(RenameRule::ScreamingSnake)),
+                    _ => Err(syn::Error::new_spanned(
+                        s,
+                        "expected one of `camelCase`, `snake_case`, `kebab-case` and \
+                             `SCREAMING_SNAKE_CASE`",
+                    )),
+                },
+                lit => Err(syn::Error::new_spanned(lit, "expected a string")),
+            },
+            _ => Err(syn::Error::new_spanned(meta, "invalid meta")),
         }
     }
 }
 
-#[derive(PartialEq)]
 enum NamedFieldAttribute {
     Flatten,
+    Elements,
+    Rename(String),
 }
 
 impl Parse for NamedFieldAttribute {
@@ -117,6 +213,28 @@ impl Parse for NamedFieldAttribute {
         let meta: NestedMeta = input.parse()?;
         match meta {
             NestedMeta::Meta(Meta::Path(p)) if p.is_ident("flatten") => Ok(Self::Flatten),
+            NestedMeta::Meta(Meta::Path(p)) if p.is_ident("elements") => Ok(Self::Elements),
+            NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("rename") => match m.lit {
+                Lit::Str(s) => Ok(Self::Rename(s.value())),
+                lit => Err(syn::Error::new_spanned(lit, "expected a string")),
+            },
+            _ => Err(syn::Error::new_spanned(meta, "invalid meta")),
+        }
+    }
+}
+
+enum VariantAttribute {
+    Rename(String),
+}
+
+impl Parse for VariantAttribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let meta: NestedMeta = input.parse()?;
+        match meta {
+            NestedMeta::Meta(Meta::NameValue(m)) if m.path.is_ident("rename") => match m.lit {
+                Lit::Str(s) => Ok(Self::Rename(s.value())),
+                lit => Err(syn::Error::new_spanned(lit, "expected a string")),
+            },
             _ => Err(syn::Error::new_spanned(meta, "invalid meta")),
         }
     }
@@ -147,30 +265,28 @@ impl Parse for NamedField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let attrs = input.call(Attribute::parse_outer)?;
 
+        let fieldmask_attrs = parse_attrs(&attrs, "fieldmask")?;
         #[allow(unused_mut)]
-        let mut is_flatten = attrs
+        let mut is_flatten = fieldmask_attrs
             .iter()
-            .filter(|attr| attr.path.is_ident("fieldmask"))
-            .map(|attr| attr.parse_args())
-            .collect::<syn::Result<Vec<_>>>()?
+            .any(|meta| matches!(meta, NamedFieldAttribute::Flatten));
+        let is_elements = fieldmask_attrs
             .iter()
-            .flat_map(|attrs: &Wrap<Punctuated<NamedFieldAttribute, Token![,]>>| &attrs.0)
-            .any(|meta| *meta == NamedFieldAttribute::Flatten);
+            .any(|meta| matches!(meta, NamedFieldAttribute::Elements));
+        let rename = fieldmask_attrs
+            .into_iter()
+            .filter_map(|meta| match meta {
+                NamedFieldAttribute::Rename(name) => Some(name),
+                _ => None,
+            })
+            .next_back();
 
         #[cfg(feature = "prost")]
         {
             is_flatten = is_flatten
-                || attrs
-                    .iter()
-                    .filter(|attr| attr.path.is_ident("prost"))
-                    .map(|attr| attr.parse_args())
-                    .collect::<syn::Result<Vec<_>>>()?
+                || parse_attrs(&attrs, "prost")?
                     .iter()
-                    .flat_map(|attrs: &Wrap<Punctuated<ProstFieldAttribute, Token![,]>>| &attrs.0)
-                    .any(|meta| match meta {
-                        ProstFieldAttribute::OneOf(_) => true,
-                        _ => false,
-                    });
+                    .any(|meta| matches!(meta, ProstFieldAttribute::OneOf(_)));
         }
 
         Ok(NamedField {
@@ -180,6 +296,8 @@ impl Parse for NamedField {
             colon_token: input.parse()?,
             ty: input.parse()?,
             is_flatten,
+            is_elements,
+            rename,
         })
     }
 }
@@ -187,8 +305,13 @@ impl Parse for NamedField {
 impl Parse for SingleTupleVariant {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let content;
+        let attrs = input.call(Attribute::parse_outer)?;
+        let rename = parse_attrs(&attrs, "fieldmask")?
+            .into_iter()
+            .map(|VariantAttribute::Rename(name)| name)
+            .next_back();
         Ok(SingleTupleVariant {
-            attrs: input.call(Attribute::parse_outer)?,
+            attrs,
             ident: {
                 let _vis: Visibility = input.parse()?;
                 input.parse()?
@@ -208,25 +331,55 @@ impl Parse for SingleTupleVariant {
                 }
                 ty
             },
+            rename,
         })
     }
 }
 
 pub struct Field<'a> {
     pub ident: &'a Ident,
-    pub ty: &'a Type,
+    /// The name of the field in paths.
+    pub name: String,
+    /// The type whose `FieldMask` is the mask of the field. It's the type of the field, or
+    /// `Elements` of it with `#[fieldmask(elements)]`.
+    pub ty: Type,
+    /// The type whose implementations the operations on the field use. It's the type of the
+    /// field, or the type of its elements with `#[fieldmask(elements)]`.
+    pub op_ty: Type,
+    /// The type whose `FieldMask` is lifted into the mask of the field. It's the type of the
+    /// field, or `T` for an `Option<T>`, which shares its mask.
+    pub lift_ty: Type,
     pub is_flatten: bool,
+    pub is_elements: bool,
+}
+
+/// `T` if `ty` is an `Option<T>`, and `ty` otherwise.
+fn lift_ty(ty: &Type) -> Type {
+    if let Type::Path(TypePath { qself: None, path }) = ty {
+        let last = path.segments.last().expect("a path has segments");
+        if last.ident == "Option" {
+            if let PathArguments::AngleBracketed(args) = &last.arguments {
+                if let [GenericArgument::Type(inner)] = args.args.iter().collect::<Vec<_>>()[..] {
+                    return inner.clone();
+                }
+            }
+        }
+    }
+    ty.clone()
 }
 
 pub struct ItemInfo<'a> {
     pub item_type: ItemType,
+    pub vis: &'a Visibility,
     pub ident: &'a Ident,
     pub generics: &'a Generics,
     pub fields: Vec<Field<'a>>,
+    /// Whether the masks of the leaf fields are packed into bits.
+    pub packed: bool,
 }
 
 impl ItemEnum {
-    pub fn get_info(&self) -> ItemInfo {
+    pub fn get_info(&self) -> ItemInfo<'_> {
         let ident = &self.ident;
         let generics = &self.generics;
         let fields = self
@@ -234,46 +387,92 @@ impl ItemEnum {
             .iter()
             .map(|v| Field {
                 ident: &v.ident,
-                ty: &v.ty,
+                name: v
+                    .rename
+                    .clone()
+                    .unwrap_or_else(|| self.rename_all.apply(&v.ident)),
+                ty: v.ty.clone(),
+                op_ty: v.ty.clone(),
+                lift_ty: lift_ty(&v.ty),
                 is_flatten: false,
+                is_elements: false,
             })
             .collect::<Vec<_>>();
         ItemInfo {
             item_type: ItemType::Enum,
+            vis: &self.vis,
             ident,
             generics,
             fields,
+            packed: self.packed,
         }
     }
 }
 
 impl ItemStruct {
-    pub fn get_info(&self) -> ItemInfo {
+    pub fn get_info(&self) -> ItemInfo<'_> {
         let ident = &self.ident;
         let generics = &self.generics;
         let fields = self
             .fields
             .iter()
-            .map(|f| Field {
-                ident: &f.ident,
-                ty: &f.ty,
-                is_flatten: f.is_flatten,
+            .map(|f| {
+                let ty: Type = if f.is_elements {
+                    let ty = &f.ty;
+                    parse_quote!(::fieldmask::Elements<#ty>)
+                } else {
+                    f.ty.clone()
+                };
+                Field {
+                    ident: &f.ident,
+                    name: f
+                        .rename
+                        .clone()
+                        .unwrap_or_else(|| self.rename_all.apply(&f.ident)),
+                    op_ty: if f.is_elements {
+                        let ty = &f.ty;
+                        parse_quote!(<#ty as ::core::iter::IntoIterator>::Item)
+                    } else {
+                        f.ty.clone()
+                    },
+                    lift_ty: lift_ty(&ty),
+                    ty,
+                    is_flatten: f.is_flatten,
+                    is_elements: f.is_elements,
+                }
             })
             .collect::<Vec<_>>();
         ItemInfo {
             item_type: ItemType::Struct,
+            vis: &self.vis,
             ident,
             generics,
             fields,
+            packed: self.packed,
         }
     }
 }
 
 impl Item {
-    pub fn get_info(&self) -> ItemInfo {
+    pub fn get_info(&self) -> ItemInfo<'_> {
         match &self {
             Item::Enum(input) => input.get_info(),
             Item::Struct(input) => input.get_info(),
         }
     }
+
+    /// Reject two fields with the same name after `rename` and `rename_all`, since a path could
+    /// only ever reach the first one.
+    fn check_names(&self) -> syn::Result<()> {
+        let mut names = HashSet::new();
+        for field in self.get_info().fields {
+            if !field.is_flatten && !names.insert(field.name.clone()) {
+                return Err(syn::Error::new_spanned(
+                    field.ident,
+                    format!("duplicate field name `{}`", field.name),
+                ));
+            }
+        }
+        Ok(())
+    }
 }